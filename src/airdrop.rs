@@ -0,0 +1,253 @@
+use crate::*;
+
+pub type CampaignId = u64;
+
+/*
+    an airdrop of series copies. The leaves of the Merkle tree are the Borsh serialized
+    (leaf index, account ID, series ID, amount) tuples and recipients claim their own leaf.
+    The NEAR deposited with the campaign pays for the storage of the claimed tokens.
+*/
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Campaign {
+    pub campaign_id: CampaignId,
+    pub creator_id: AccountId,
+    pub merkle_root: Base64VecU8,
+    //number of leaves of the tree, leaf indexes go from 0 to leaf_count - 1
+    pub leaf_count: u64,
+    //what's left of the deposit to pay for the storage of the claimed tokens
+    pub storage_budget: U128,
+    //series the leaves can mint copies of, all of them created by the campaign creator
+    pub series_ids: Vec<SeriesId>,
+    pub claimed: u64,
+    pub closed: bool,
+}
+
+#[near_bindgen]
+impl Contract {
+    /*
+        OPEN A CLAIM CAMPAIGN FOR COPIES OF YOUR OWN FREE SERIES ---
+        THE DEPOSIT MINUS THE CAMPAIGN STORAGE PAYS FOR THE CLAIMED TOKENS
+    */
+    #[payable]
+    pub fn create_campaign(
+        &mut self,
+        merkle_root: Base64VecU8,
+        leaf_count: u64,
+        series_ids: Vec<SeriesId>,
+    ) -> Campaign {
+        self.assert_role(Role::Admin);
+        parse_merkle_root(&merkle_root);
+        assert!(leaf_count > 0, "CAMPAIGN NEEDS AT LEAST ONE LEAF");
+        let creator_id = env::predecessor_account_id();
+        for series_id in series_ids.iter() {
+            let series = self.internal_get_series(series_id).expect("SERIES DOESNT EXIST");
+            assert_airdroppable(&series, &creator_id);
+        }
+
+        let initial_storage_usage = env::storage_usage();
+
+        let campaign_id = self.next_campaign_id;
+        self.next_campaign_id += 1;
+        let mut campaign = Campaign {
+            campaign_id,
+            creator_id,
+            merkle_root,
+            leaf_count,
+            storage_budget: U128(0),
+            series_ids,
+            claimed: 0,
+            closed: false,
+        };
+        self.campaigns.insert(&campaign_id, &campaign);
+
+        let storage_cost = env::storage_byte_cost()
+            * Balance::from(env::storage_usage() - initial_storage_usage);
+        let deposit = env::attached_deposit();
+        assert!(
+            deposit > storage_cost,
+            "DEPOSIT MUST COVER STORAGE OF :{} AND THE CLAIMS",
+            storage_cost
+        );
+        campaign.storage_budget = U128(deposit - storage_cost);
+        self.campaigns.insert(&campaign_id, &campaign);
+
+        campaign
+    }
+
+    // CLAIM THE TOKENS OF A LEAF --- THEY ARE MINTED TO THE CALLER
+    #[payable]
+    pub fn claim(
+        &mut self,
+        campaign_id: CampaignId,
+        index: u64,
+        series_id: SeriesId,
+        amount: u32,
+        proof: Vec<Base64VecU8>,
+    ) -> Vec<TokenId> {
+        self.assert_not_paused(PauseFeature::Minting);
+        let mut campaign = self.campaigns.get(&campaign_id).expect("CAMPAIGN DOESNT EXIST");
+        assert!(!campaign.closed, "CAMPAIGN IS CLOSED");
+        assert!(index < campaign.leaf_count, "LEAF INDEX OUT OF RANGE");
+        assert!(amount > 0, "NOTHING TO CLAIM");
+        assert!(
+            amount <= 125,
+            "CANNOT MINT MORE THAN 125 TOKENS DUE TO GAS LIMITS"
+        );
+
+        let claimer_id = env::predecessor_account_id();
+        let root = parse_merkle_root(&campaign.merkle_root);
+        let leaf = merkle_leaf(&(index, &claimer_id, series_id, amount));
+        assert!(
+            verify_merkle_proof(&root, leaf, &proof),
+            "INVALID CLAIM PROOF"
+        );
+
+        let initial_storage_usage = env::storage_usage();
+
+        //a leaf can only be claimed once
+        assert!(
+            self.internal_set_claimed(campaign_id, index),
+            "ALREADY CLAIMED"
+        );
+
+        assert!(
+            campaign.series_ids.contains(&series_id),
+            "SERIES IS NOT PART OF THE CAMPAIGN"
+        );
+        let mut series = self.internal_get_series(&series_id).expect("SERIES DOESNT EXIST");
+        assert_airdroppable(&series, &campaign.creator_id);
+        let mut token_ids = Vec::new();
+        for _ in 0..amount {
+            token_ids.push(self.internal_mint_series_copy(series, &claimer_id, None, None));
+            series = self.internal_get_series(&series_id).unwrap();
+        }
+        log_nft_mint(claimer_id.as_str(), &token_ids, None);
+        campaign.claimed += 1;
+
+        //the campaign pays for the storage, if its budget ran out the claimer does
+        let storage_used = env::storage_usage() - initial_storage_usage;
+        let storage_cost = env::storage_byte_cost() * Balance::from(storage_used);
+        if storage_cost <= campaign.storage_budget.0 {
+            campaign.storage_budget = U128(campaign.storage_budget.0 - storage_cost);
+            let refund = env::attached_deposit();
+            if refund > 1 {
+                Promise::new(claimer_id).transfer(refund);
+            }
+        } else {
+            self.internal_refund_deposit(storage_used);
+        }
+        self.campaigns.insert(&campaign_id, &campaign);
+
+        token_ids
+    }
+
+    // CLOSE A CAMPAIGN --- WHAT'S LEFT OF ITS STORAGE BUDGET GOES BACK TO ITS CREATOR
+    #[payable]
+    pub fn close_campaign(&mut self, campaign_id: CampaignId) -> Campaign {
+        assert_one_yocto();
+        self.assert_role(Role::Admin);
+        let mut campaign = self.campaigns.get(&campaign_id).expect("CAMPAIGN DOESNT EXIST");
+        assert!(!campaign.closed, "CAMPAIGN IS CLOSED");
+
+        let unused = campaign.storage_budget.0;
+        campaign.storage_budget = U128(0);
+        campaign.closed = true;
+        self.campaigns.insert(&campaign_id, &campaign);
+
+        if unused > 0 {
+            Promise::new(campaign.creator_id.clone()).transfer(unused);
+        }
+
+        campaign
+    }
+
+    pub fn get_campaign(&self, campaign_id: CampaignId) -> Option<Campaign> {
+        self.campaigns.get(&campaign_id)
+    }
+
+    pub fn is_claimed(&self, campaign_id: CampaignId, index: u64) -> bool {
+        let word = self
+            .claimed_bitmaps
+            .get(&(campaign_id, index / 64))
+            .unwrap_or(0);
+        word & (1 << (index % 64)) != 0
+    }
+}
+
+impl Contract {
+    //mark a leaf as claimed, returns false if it already was. Each stored word holds 64 leaves.
+    pub(crate) fn internal_set_claimed(&mut self, campaign_id: CampaignId, index: u64) -> bool {
+        let key = (campaign_id, index / 64);
+        let word = self.claimed_bitmaps.get(&key).unwrap_or(0);
+        let bit = 1 << (index % 64);
+        if word & bit != 0 {
+            return false;
+        }
+        self.claimed_bitmaps.insert(&key, &(word | bit));
+        true
+    }
+}
+
+//only the creator of a series can give its copies away, and only if they aren't sold
+fn assert_airdroppable(series: &Series, creator_id: &AccountId) {
+    assert_eq!(
+        &series.creator_id, creator_id,
+        "ONLY THE SERIES CREATOR CAN AIRDROP ITS COPIES"
+    );
+    assert!(
+        !series.lazy_mint && series.price.is_none(),
+        "LAZY OR PRICED SERIES CANNOT BE AIRDROPPED"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    //alice.near holds the minter role and creates a free series 0 and a priced series 1
+    fn contract_with_series() -> Contract {
+        let mut contract = new_contract();
+        set_context("owner.near", ONE_NEAR, 0);
+        contract.grant_role(Role::Minter, account("alice.near"));
+        contract.grant_role(Role::Admin, account("alice.near"));
+        set_context("alice.near", ONE_NEAR, 0);
+        contract.create_series(empty_metadata(), None, None, None);
+        contract.create_series(empty_metadata(), None, Some(U128(ONE_NEAR)), None);
+        contract
+    }
+
+    fn root_of(leaf: CryptoHash) -> Base64VecU8 {
+        Base64VecU8(leaf.to_vec())
+    }
+
+    #[test]
+    #[should_panic(expected = "ONLY THE SERIES CREATOR CAN AIRDROP ITS COPIES")]
+    fn admin_cannot_airdrop_someone_elses_series() {
+        let mut contract = contract_with_series();
+        set_context("owner.near", ONE_NEAR, 0);
+        contract.create_campaign(root_of([0; 32]), 1, vec![0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "LAZY OR PRICED SERIES CANNOT BE AIRDROPPED")]
+    fn priced_series_cannot_be_airdropped() {
+        let mut contract = contract_with_series();
+        set_context("alice.near", ONE_NEAR, 0);
+        contract.create_campaign(root_of([0; 32]), 1, vec![1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "SERIES IS NOT PART OF THE CAMPAIGN")]
+    fn claim_of_a_series_outside_the_campaign_is_rejected() {
+        let mut contract = contract_with_series();
+        //the only leaf of the tree lets bob.near claim a copy of the priced series
+        let leaf = merkle_leaf(&(0u64, &account("bob.near"), 1 as SeriesId, 1u32));
+        set_context("alice.near", ONE_NEAR, 0);
+        let campaign = contract.create_campaign(root_of(leaf), 1, vec![0]);
+
+        set_context("bob.near", 0, 0);
+        contract.claim(campaign.campaign_id, 0, 1, 1, vec![]);
+    }
+}
@@ -43,43 +43,29 @@ impl NonFungibleTokenCore for Contract {
             The user needs to attach enough to pay for storage on the contract
         */
         assert_at_least_one_yocto();
+        //an expired token burned by the validity policy can't be approved, the deposit goes back
+        if self.internal_expire_on_touch(&token_id) == Some(ExpiryAction::Burn) {
+            Promise::new(env::predecessor_account_id()).transfer(env::attached_deposit());
+            return;
+        }
 
-        //get the token object from the token ID
-        let mut token = self.tokens_by_id.get(&token_id).expect("No token");
-
-        //make sure that the person calling the function is the owner of the token
-        assert_eq!(
-            &env::predecessor_account_id(),
-            &token.owner_id,
-            "Predecessor must be the token owner."
-        );
-
-        //get the next approval ID if we need a new approval
-        let approval_id: u64 = token.next_approval_id;
+        //approve the account and get back the token, the approval ID and the storage used
+        let (token, approval_id, storage_used) = self.internal_approve(&token_id, &account_id);
 
-        //check if the account has been approved already for this token
-        let is_new_approval = token
-            .approved_account_ids
-            //insert returns none if the key was not present.  
-            .insert(account_id.clone(), approval_id)
-            //if the key was not present, .is_none() will return true so it is a new approval.
-            .is_none();
+        //refund any excess storage attached by the user. If the user didn't attach enough, panic. 
+        self.internal_refund_deposit(storage_used);
 
-        //if it was a new approval, we need to calculate how much storage is being used to add the account.
-        let storage_used = if is_new_approval {
-            bytes_for_approved_account_id(&account_id)
-        //if it was not a new approval, we used no storage.
-        } else {
-            0
+        let nft_approve_log: EventLog = EventLog {
+            standard: BLOCKPERKS_STANDARD_NAME.to_string(),
+            version: BLOCKPERKS_EVENT_VERSION.to_string(),
+            event: EventLogVariant::NftApprove(vec![NftApproveLog {
+                owner_id: token.owner_id.to_string(),
+                token_id: token_id.to_string(),
+                account_id: account_id.to_string(),
+                approval_id,
+            }]),
         };
-
-        //increment the token's next approval ID by 1
-        token.next_approval_id += 1;
-        //insert the token back into the tokens_by_id collection
-        self.tokens_by_id.insert(&token_id, &token);
-
-        //refund any excess storage attached by the user. If the user didn't attach enough, panic. 
-        refund_deposit(storage_used);
+        env::log_str(&nft_approve_log.to_string());
 
         //if some message was passed into the function, we initiate a cross contract call on the
         //account we're giving access to. 
@@ -103,7 +89,7 @@ impl NonFungibleTokenCore for Contract {
         approval_id: Option<u64>,
     ) -> bool {
         //get the token object from the token_id
-        let token = self.tokens_by_id.get(&token_id).expect("No token");
+        let token = self.internal_get_token(&token_id).expect("No token");
 
         //get the approval number for the passed in account ID
 		let approval = token.approved_account_ids.get(&approved_account_id);
@@ -129,24 +115,24 @@ impl NonFungibleTokenCore for Contract {
     fn nft_revoke(&mut self, token_id: TokenId, account_id: AccountId) {
         //assert that the user attached exactly 1 yoctoNEAR for security reasons
         assert_one_yocto();
-        //get the token object using the passed in token_id
-        let mut token = self.tokens_by_id.get(&token_id).expect("No token");
-
-        //get the caller of the function and assert that they are the owner of the token
+        //get the caller of the function, the revoke asserts that they are the owner of the token
         let predecessor_account_id = env::predecessor_account_id();
-        assert_eq!(&predecessor_account_id, &token.owner_id);
 
         //if the account ID was in the token's approval, we remove it and the if statement logic executes
-        if token
-            .approved_account_ids
-            .remove(&account_id)
-            .is_some()
-        {
+        if let Some(token) = self.internal_revoke(&token_id, &account_id) {
             //refund the funds released by removing the approved_account_id to the caller of the function
-            refund_approved_account_ids_iter(predecessor_account_id, [account_id].iter());
+            refund_approved_account_ids_iter(predecessor_account_id, [account_id.clone()].iter());
 
-            //insert the token back into the tokens_by_id collection with the account_id removed from the approval list
-            self.tokens_by_id.insert(&token_id, &token);
+            let nft_revoke_log: EventLog = EventLog {
+                standard: BLOCKPERKS_STANDARD_NAME.to_string(),
+                version: BLOCKPERKS_EVENT_VERSION.to_string(),
+                event: EventLogVariant::NftRevoke(vec![NftRevokeLog {
+                    owner_id: token.owner_id.to_string(),
+                    token_id: token_id.to_string(),
+                    account_ids: vec![account_id.to_string()],
+                }]),
+            };
+            env::log_str(&nft_revoke_log.to_string());
         }
     }
 
@@ -157,7 +143,7 @@ impl NonFungibleTokenCore for Contract {
         assert_one_yocto();
 
         //get the token object from the passed in token ID
-        let mut token = self.tokens_by_id.get(&token_id).expect("No token");
+        let mut token = self.internal_get_token(&token_id).expect("No token");
         //get the caller and make sure they are the owner of the tokens
         let predecessor_account_id = env::predecessor_account_id();
         assert_eq!(&predecessor_account_id, &token.owner_id);
@@ -167,9 +153,214 @@ impl NonFungibleTokenCore for Contract {
             //refund the approved account IDs to the caller of the function
             refund_approved_account_ids(predecessor_account_id, &token.approved_account_ids);
             //clear the approved account IDs
-            token.approved_account_ids.clear();
+            let account_ids = token
+                .approved_account_ids
+                .drain()
+                .map(|(account_id, _)| account_id.to_string())
+                .collect();
             //insert the token back into the tokens_by_id collection with the approved account IDs cleared
-            self.tokens_by_id.insert(&token_id, &token);
+            self.internal_insert_token(&token_id, &token);
+
+            let nft_revoke_log: EventLog = EventLog {
+                standard: BLOCKPERKS_STANDARD_NAME.to_string(),
+                version: BLOCKPERKS_EVENT_VERSION.to_string(),
+                event: EventLogVariant::NftRevoke(vec![NftRevokeLog {
+                    owner_id: token.owner_id.to_string(),
+                    token_id: token_id.to_string(),
+                    account_ids,
+                }]),
+            };
+            env::log_str(&nft_revoke_log.to_string());
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /*
+        APPROVE AN ACCOUNT ON MANY OF YOUR TOKENS AT ONCE --- ATTACH THE STORAGE OF ALL THE NEW APPROVALS.
+        RETURNS THE APPROVAL IDS IN ORDER, EXPIRED TOKENS BURNED BY THE VALIDITY POLICY ARE SKIPPED
+    */
+    #[payable]
+    pub fn nft_batch_approve(&mut self, token_ids: Vec<TokenId>, account_id: AccountId) -> Vec<u64> {
+        assert_at_least_one_yocto();
+        assert!(
+            token_ids.len() <= MAX_BATCH_SIZE,
+            "CANNOT APPROVE MORE THAN {} TOKENS AT ONCE",
+            MAX_BATCH_SIZE
+        );
+
+        let mut approval_ids = Vec::new();
+        let mut approve_logs = Vec::new();
+        let mut storage_used = 0;
+        for token_id in token_ids {
+            //an expired token burned by the validity policy has nothing left to approve
+            if self.internal_expire_on_touch(&token_id) == Some(ExpiryAction::Burn) {
+                continue;
+            }
+            let (token, approval_id, used) = self.internal_approve(&token_id, &account_id);
+            storage_used += used;
+            approval_ids.push(approval_id);
+            approve_logs.push(NftApproveLog {
+                owner_id: token.owner_id.to_string(),
+                token_id: token_id.to_string(),
+                account_id: account_id.to_string(),
+                approval_id,
+            });
+        }
+
+        //one refund for the storage of every new approval
+        self.internal_refund_deposit(storage_used);
+
+        if !approve_logs.is_empty() {
+            let nft_approve_log: EventLog = EventLog {
+                standard: BLOCKPERKS_STANDARD_NAME.to_string(),
+                version: BLOCKPERKS_EVENT_VERSION.to_string(),
+                event: EventLogVariant::NftApprove(approve_logs),
+            };
+            env::log_str(&nft_approve_log.to_string());
+        }
+
+        approval_ids
+    }
+
+    // REVOKE AN ACCOUNT ON MANY OF YOUR TOKENS AT ONCE
+    #[payable]
+    pub fn nft_batch_revoke(&mut self, token_ids: Vec<TokenId>, account_id: AccountId) {
+        assert_one_yocto();
+        assert!(
+            token_ids.len() <= MAX_BATCH_SIZE,
+            "CANNOT REVOKE MORE THAN {} TOKENS AT ONCE",
+            MAX_BATCH_SIZE
+        );
+
+        let mut revoke_logs = Vec::new();
+        for token_id in token_ids {
+            if let Some(token) = self.internal_revoke(&token_id, &account_id) {
+                revoke_logs.push(NftRevokeLog {
+                    owner_id: token.owner_id.to_string(),
+                    token_id: token_id.to_string(),
+                    account_ids: vec![account_id.to_string()],
+                });
+            }
         }
+
+        if !revoke_logs.is_empty() {
+            //one refund for the storage of every removed approval. repeat_n is newer than the toolchains the contract targets
+            #[allow(clippy::manual_repeat_n)]
+            let revoked_account_ids = std::iter::repeat(&account_id).take(revoke_logs.len());
+            refund_approved_account_ids_iter(env::predecessor_account_id(), revoked_account_ids);
+
+            let nft_revoke_log: EventLog = EventLog {
+                standard: BLOCKPERKS_STANDARD_NAME.to_string(),
+                version: BLOCKPERKS_EVENT_VERSION.to_string(),
+                event: EventLogVariant::NftRevoke(revoke_logs),
+            };
+            env::log_str(&nft_revoke_log.to_string());
+        }
+    }
+}
+
+impl Contract {
+    //approve an account on a token of the caller, returns the token, the approval ID and the storage used
+    pub(crate) fn internal_approve(&mut self, token_id: &TokenId, account_id: &AccountId) -> (Token, u64, u64) {
+        //get the token object from the token ID
+        let mut token = self.internal_get_token(token_id).expect("No token");
+
+        //make sure that the person calling the function is the owner of the token
+        assert_eq!(
+            &env::predecessor_account_id(),
+            &token.owner_id,
+            "Predecessor must be the token owner."
+        );
+
+        //nobody can be approved to move a bound token
+        self.assert_transferable(token_id, &token);
+
+        //get the next approval ID if we need a new approval
+        let approval_id: u64 = token.next_approval_id;
+
+        //check if the account has been approved already for this token
+        let is_new_approval = token
+            .approved_account_ids
+            //insert returns none if the key was not present.  
+            .insert(account_id.clone(), approval_id)
+            //if the key was not present, .is_none() will return true so it is a new approval.
+            .is_none();
+
+        //if it was a new approval, we need to calculate how much storage is being used to add the account.
+        let storage_used = if is_new_approval {
+            bytes_for_approved_account_id(account_id)
+        //if it was not a new approval, we used no storage.
+        } else {
+            0
+        };
+
+        //increment the token's next approval ID by 1
+        token.next_approval_id += 1;
+        //insert the token back into the tokens_by_id collection
+        self.internal_insert_token(token_id, &token);
+
+        (token, approval_id, storage_used)
+    }
+
+    //remove an account from the approvals of a token of the caller, returns the token if it was approved
+    pub(crate) fn internal_revoke(&mut self, token_id: &TokenId, account_id: &AccountId) -> Option<Token> {
+        //get the token object using the passed in token_id
+        let mut token = self.internal_get_token(token_id).expect("No token");
+
+        //assert that the caller is the owner of the token
+        assert_eq!(&env::predecessor_account_id(), &token.owner_id);
+
+        token.approved_account_ids.remove(account_id)?;
+
+        //insert the token back into the tokens_by_id collection with the account_id removed from the approval list
+        self.internal_insert_token(token_id, &token);
+        Some(token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::mock::VmAction;
+    use near_sdk::test_utils::{get_created_receipts, get_logs};
+
+    #[test]
+    fn batch_approve_and_revoke_log_once_and_refund_once() {
+        let mut contract = new_contract();
+        for token_id in 1..=3 {
+            insert_token(&mut contract, token_id, "alice.near");
+        }
+
+        set_context("alice.near", ONE_NEAR, 0);
+        assert_eq!(contract.nft_batch_approve(vec![1, 2], account("market.near")), vec![0, 0]);
+        let logs = get_logs();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].matches(r#""account_id":"market.near""#).count(), 2);
+        //the excess of the deposit is refunded once
+        assert_eq!(get_created_receipts().len(), 1);
+
+        //token 3 was never approved, nothing is revoked or refunded for it
+        set_context("alice.near", 1, 0);
+        contract.nft_batch_revoke(vec![1, 2, 3], account("market.near"));
+        for token_id in 1..=3 {
+            assert!(contract.internal_get_token(&token_id).unwrap().approved_account_ids.is_empty());
+        }
+        let logs = get_logs();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].matches(r#""token_id""#).count(), 2);
+
+        let receipts = get_created_receipts();
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].receiver_id, account("alice.near"));
+        let released = 2 * bytes_for_approved_account_id(&account("market.near"));
+        assert_eq!(
+            receipts[0].actions,
+            vec![VmAction::Transfer {
+                deposit: Balance::from(released) * env::storage_byte_cost()
+            }]
+        );
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,251 @@
+use crate::*;
+
+//the highest bid placed on an auction so far
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Bid {
+    pub bidder_id: AccountId,
+    pub amount: U128,
+}
+
+//an English auction running on a token. The attached NEAR of the highest bid is held by the contract.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Auction {
+    pub token_id: TokenId,
+    //owner of the token when the auction was opened (the seller)
+    pub owner_id: AccountId,
+    //the first bid has to be at least the reserve price
+    pub reserve_price: U128,
+    //every next bid has to be at least the highest bid + this increment
+    pub min_bid_increment: U128,
+    //block timestamp in nanoseconds after which no bids are accepted and the auction can be settled
+    pub ends_at: u64,
+    pub highest_bid: Option<Bid>,
+}
+
+#[near_bindgen]
+impl Contract {
+    // OPEN AN ENGLISH AUCTION ON A TOKEN --- THE TOKEN IS DELISTED FROM FIXED PRICE SALE WHILE IT RUNS
+    #[payable]
+    pub fn create_auction(
+        &mut self,
+        token_id: TokenId,
+        reserve_price: U128,
+        min_bid_increment: U128,
+        ends_at: u64,
+    ) -> Auction {
+        assert_at_least_one_yocto();
+        let initial_storage_usage = env::storage_usage();
+
+        let mut token_data = self.internal_get_token(&token_id).expect("TOKEN DOESNT EXIST");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_data.owner_id,
+            "ONLY TOKEN OWNER CAN CREATE AN AUCTION"
+        );
+        self.assert_transferable(&token_id, &token_data);
+        self.assert_not_rented(&token_id);
+        self.assert_not_expired_for_sale(&token_id);
+        self.assert_not_on_auction(&token_id);
+        assert!(
+            self.dutch_listings.get(&token_id).is_none(),
+            "TOKEN HAS A DUTCH LISTING"
+        );
+        assert!(
+            ends_at > env::block_timestamp(),
+            "AUCTION END MUST BE IN THE FUTURE"
+        );
+        assert!(min_bid_increment.0 > 0, "MINIMUM BID INCREMENT MUST BE > 0");
+
+        //the token can't be bought at a fixed price while it is on auction
+        token_data.price = None;
+        token_data.ft_token_id = None;
+        self.internal_insert_token(&token_id, &token_data);
+
+        let auction = Auction {
+            token_id,
+            owner_id: token_data.owner_id,
+            reserve_price,
+            min_bid_increment,
+            ends_at,
+            highest_bid: None,
+        };
+        self.auctions.insert(&token_id, &auction);
+
+        let auction_create_log: EventLog = EventLog {
+            standard: BLOCKPERKS_STANDARD_NAME.to_string(),
+            version: BLOCKPERKS_EVENT_VERSION.to_string(),
+            event: EventLogVariant::AuctionCreate(vec![AuctionCreateLog {
+                owner_id: auction.owner_id.to_string(),
+                token_id: token_id.to_string(),
+                reserve_price,
+                min_bid_increment,
+                ends_at,
+            }]),
+        };
+        env::log_str(&auction_create_log.to_string());
+
+        //refund any excess storage if the user attached too much. Whatever wasn't attached is taken from the storage balance.
+        let required_storage_in_bytes = env::storage_usage() - initial_storage_usage;
+        self.internal_refund_deposit(required_storage_in_bytes);
+
+        auction
+    }
+
+    // BID ON AN AUCTION WITH THE ATTACHED DEPOSIT --- THE PREVIOUS HIGHEST BIDDER IS REFUNDED
+    #[payable]
+    pub fn place_bid(&mut self, token_id: TokenId) -> Auction {
+        self.assert_not_paused(PauseFeature::Trading);
+        let mut auction = self.auctions.get(&token_id).expect("NO AUCTION FOR TOKEN");
+        let bidder_id = env::predecessor_account_id();
+        let amount = env::attached_deposit();
+
+        assert!(
+            env::block_timestamp() < auction.ends_at,
+            "AUCTION HAS ENDED"
+        );
+        assert_ne!(
+            bidder_id, auction.owner_id,
+            "YOU CANNOT BID ON YOUR OWN AUCTION"
+        );
+
+        //the first bid has to meet the reserve price, every next one has to outbid by the increment
+        let min_bid = match &auction.highest_bid {
+            Some(bid) => bid.amount.0 + auction.min_bid_increment.0,
+            None => auction.reserve_price.0,
+        };
+        assert!(amount >= min_bid, "BID MUST BE AT LEAST :{}", min_bid);
+
+        //refund the outbid bidder
+        if let Some(previous_bid) = auction.highest_bid.take() {
+            Promise::new(previous_bid.bidder_id).transfer(previous_bid.amount.0);
+        }
+
+        auction.highest_bid = Some(Bid {
+            bidder_id: bidder_id.clone(),
+            amount: U128(amount),
+        });
+        self.auctions.insert(&token_id, &auction);
+
+        let auction_bid_log: EventLog = EventLog {
+            standard: BLOCKPERKS_STANDARD_NAME.to_string(),
+            version: BLOCKPERKS_EVENT_VERSION.to_string(),
+            event: EventLogVariant::AuctionBid(vec![AuctionBidLog {
+                bidder_id: bidder_id.to_string(),
+                token_id: token_id.to_string(),
+                amount: U128(amount),
+            }]),
+        };
+        env::log_str(&auction_bid_log.to_string());
+
+        auction
+    }
+
+    // SETTLE AN ENDED AUCTION --- PAYS OUT THE WINNING BID LIKE nft_buy AND HANDS THE TOKEN TO THE WINNER
+    pub fn settle_auction(&mut self, token_id: TokenId) -> Auction {
+        self.assert_not_paused(PauseFeature::Trading);
+        let auction = self.auctions.get(&token_id).expect("NO AUCTION FOR TOKEN");
+        assert!(
+            env::block_timestamp() >= auction.ends_at,
+            "AUCTION HAS NOT ENDED YET"
+        );
+
+        //the auction is removed first so the token is no longer locked
+        self.auctions.remove(&token_id);
+
+        let mut sold = false;
+        if let Some(bid) = &auction.highest_bid {
+            let token_data = self.internal_get_token(&token_id).expect("TOKEN DOESNT EXIST");
+
+            //the token may have been bound or rented since the bid was placed, the winner then gets their bid back
+            if !self.internal_is_transferable(&token_id, &token_data) || self.internal_is_rented(&token_id) {
+                Promise::new(bid.bidder_id.clone()).transfer(bid.amount.0);
+            } else {
+                //pay out the royalties, split payments, treasury fee and the seller
+                let payout = self.internal_sale_payout(&token_data, bid.amount.0);
+                log_nft_sale(
+                    &token_id,
+                    &token_data.owner_id,
+                    &bid.bidder_id,
+                    bid.amount.0,
+                    None,
+                    &payout,
+                );
+                self.internal_distribute_payout(payout);
+
+                //hand the token over to the winner
+                self.internal_sale_transfer(&token_id, token_data, &bid.bidder_id);
+                log_nft_transfer(
+                    None,
+                    auction.owner_id.as_str(),
+                    bid.bidder_id.as_str(),
+                    &[token_id],
+                    None,
+                );
+                sold = true;
+            }
+        }
+
+        //no winner is logged when the bid was refunded
+        let winning_bid = auction.highest_bid.as_ref().filter(|_| sold);
+        let auction_settle_log: EventLog = EventLog {
+            standard: BLOCKPERKS_STANDARD_NAME.to_string(),
+            version: BLOCKPERKS_EVENT_VERSION.to_string(),
+            event: EventLogVariant::AuctionSettle(vec![AuctionSettleLog {
+                owner_id: auction.owner_id.to_string(),
+                token_id: token_id.to_string(),
+                winner_id: winning_bid.map(|bid| bid.bidder_id.to_string()),
+                amount: winning_bid.map(|bid| bid.amount),
+            }]),
+        };
+        env::log_str(&auction_settle_log.to_string());
+
+        auction
+    }
+    // CANCEL AN AUCTION THAT DIDN'T RECEIVE ANY BID YET
+    #[payable]
+    pub fn cancel_auction(&mut self, token_id: TokenId) {
+        assert_one_yocto();
+        let auction = self.auctions.get(&token_id).expect("NO AUCTION FOR TOKEN");
+        assert_eq!(
+            env::predecessor_account_id(),
+            auction.owner_id,
+            "ONLY AUCTION OWNER CAN CANCEL"
+        );
+        assert!(
+            auction.highest_bid.is_none(),
+            "CANNOT CANCEL AN AUCTION WITH BIDS"
+        );
+
+        self.auctions.remove(&token_id);
+
+        let auction_cancel_log: EventLog = EventLog {
+            standard: BLOCKPERKS_STANDARD_NAME.to_string(),
+            version: BLOCKPERKS_EVENT_VERSION.to_string(),
+            event: EventLogVariant::AuctionCancel(vec![AuctionCancelLog {
+                owner_id: auction.owner_id.to_string(),
+                token_id: token_id.to_string(),
+            }]),
+        };
+        env::log_str(&auction_cancel_log.to_string());
+    }
+
+    //get the auction running on a token
+    pub fn get_auction(&self, token_id: TokenId) -> Option<Auction> {
+        self.auctions.get(&token_id)
+    }
+
+    //Query for the auctions that haven't been settled yet using pagination
+    pub fn get_auctions(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<Auction> {
+        //where to start pagination - if we have a from_index, we'll use that - otherwise start from 0 index
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        self.auctions.values()
+            //skip to the index we specified in the start variable
+            .skip(start as usize)
+            //take the first "limit" elements in the vector. If we didn't specify a limit, use 50
+            .take(limit.unwrap_or(50) as usize)
+            .collect()
+    }
+}
@@ -0,0 +1,401 @@
+use crate::*;
+use near_sdk::serde_json::json;
+use near_sdk::{Gas, PromiseResult};
+
+/*
+    NEAR the drop key can spend on gas. It isn't refunded: every claim signed with the key, failed ones
+    included, is paid from the contract balance and the contract can't tell how much of it was spent.
+*/
+const DROP_KEY_ALLOWANCE: Balance = 100_000_000_000_000_000_000_000;
+const GAS_FOR_CREATE_ACCOUNT: Gas = Gas(28_000_000_000_000);
+const GAS_FOR_RESOLVE_CREATE_ACCOUNT: Gas = Gas(13_000_000_000_000);
+//methods the drop key is allowed to call on this contract
+const DROP_KEY_METHODS: &str = "claim_drop,create_account_and_claim";
+
+/*
+    a token held in escrow by the contract until someone holding the private key of the drop
+    claims it. The key is a function call access key of the contract restricted to the claim methods.
+*/
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Drop {
+    pub token_id: TokenId,
+    pub creator_id: AccountId,
+    pub public_key: PublicKey,
+    //block timestamp in nanoseconds after which the creator can take the token back
+    pub expires_at: u64,
+    //NEAR given to the account created by the claim, refunded to the creator otherwise
+    pub funding: U128,
+}
+
+#[near_bindgen]
+impl Contract {
+    /*
+        PUT ONE OF YOUR TOKENS IN ESCROW FOR WHOEVER HOLDS THE PRIVATE KEY OF `public_key`.
+        ATTACH THE STORAGE, THE KEY ALLOWANCE AND THE FUNDING OF THE ACCOUNT A CLAIM CAN CREATE.
+        THE KEY ALLOWANCE PAYS FOR THE CLAIMS AND IS NOT REFUNDED.
+    */
+    #[payable]
+    pub fn create_drop(&mut self, token_id: TokenId, public_key: PublicKey, expires_at: u64) -> Drop {
+        self.assert_not_paused(PauseFeature::Transfers);
+        assert!(
+            expires_at > env::block_timestamp(),
+            "DROP EXPIRY MUST BE IN THE FUTURE"
+        );
+        assert!(
+            self.drops.get(&public_key).is_none(),
+            "KEY IS ALREADY USED BY A DROP"
+        );
+        let creator_id = env::predecessor_account_id();
+        let token = self.internal_get_token(&token_id).expect("TOKEN DOESNT EXIST");
+        assert_eq!(token.owner_id, creator_id, "ONLY THE TOKEN OWNER CAN DROP IT");
+        let initial_storage_usage = env::storage_usage();
+
+        //the contract holds the token, unlisted, until it's claimed
+        self.internal_transfer(
+            &creator_id,
+            &env::current_account_id(),
+            &token_id,
+            None,
+            None,
+        );
+        let mut token = self.internal_get_token(&token_id).unwrap();
+        token.price = None;
+        token.ft_token_id = None;
+        self.internal_insert_token(&token_id, &token);
+
+        let mut drop = Drop {
+            token_id,
+            creator_id,
+            public_key: public_key.clone(),
+            expires_at,
+            funding: U128(0),
+        };
+        self.drops.insert(&public_key, &drop);
+
+        let storage_cost = env::storage_byte_cost()
+            * Balance::from(env::storage_usage().saturating_sub(initial_storage_usage));
+        let deposit = env::attached_deposit();
+        assert!(
+            deposit >= storage_cost + DROP_KEY_ALLOWANCE,
+            "DEPOSIT MUST COVER STORAGE OF :{} AND THE KEY ALLOWANCE OF :{}",
+            storage_cost,
+            DROP_KEY_ALLOWANCE
+        );
+        drop.funding = U128(deposit - storage_cost - DROP_KEY_ALLOWANCE);
+        self.drops.insert(&public_key, &drop);
+
+        Promise::new(env::current_account_id()).add_access_key(
+            public_key,
+            DROP_KEY_ALLOWANCE,
+            env::current_account_id(),
+            DROP_KEY_METHODS.to_string(),
+        );
+
+        drop
+    }
+
+    // CLAIM A DROP TO AN EXISTING ACCOUNT --- SIGNED WITH THE DROP KEY
+    pub fn claim_drop(&mut self, account_id: AccountId) {
+        let initial_storage_usage = env::storage_usage();
+        let drop = self.internal_take_drop();
+
+        self.internal_release_drop_token(&drop, &account_id);
+        let freed_storage = initial_storage_usage.saturating_sub(env::storage_usage());
+        self.internal_close_drop(drop, freed_storage, true);
+    }
+
+    // CREATE A NEW ACCOUNT WITH THE FUNDING OF A DROP AND CLAIM IT TO THAT ACCOUNT --- SIGNED WITH THE DROP KEY
+    pub fn create_account_and_claim(
+        &mut self,
+        new_account_id: AccountId,
+        new_public_key: PublicKey,
+    ) -> Promise {
+        let initial_storage_usage = env::storage_usage();
+        let drop = self.internal_take_drop();
+        let freed_storage = initial_storage_usage.saturating_sub(env::storage_usage());
+
+        //the account is created by the linkdrop contract (`near` or `testnet`) of the network this contract lives on
+        let current_account_id = env::current_account_id();
+        let linkdrop_account_id: AccountId = current_account_id
+            .as_str()
+            .rsplit('.')
+            .next()
+            .unwrap()
+            .parse()
+            .unwrap();
+
+        let args = json!({ "new_account_id": new_account_id, "new_public_key": new_public_key });
+        Promise::new(linkdrop_account_id)
+            .function_call(
+                "create_account".to_string(),
+                args.to_string().into_bytes(),
+                drop.funding.0,
+                GAS_FOR_CREATE_ACCOUNT,
+            )
+        .then(
+            Self::ext(current_account_id)
+                .with_static_gas(GAS_FOR_RESOLVE_CREATE_ACCOUNT)
+                .resolve_create_account(drop, new_account_id, freed_storage)
+        )
+    }
+
+    /*
+        hands the token over once the account exists. If it couldn't be created, the linkdrop contract
+        refunds the funding and the token goes back to the creator. The key is deleted either way.
+    */
+    #[private]
+    pub fn resolve_create_account(
+        &mut self,
+        drop: Drop,
+        new_account_id: AccountId,
+        freed_storage: u64,
+    ) -> bool {
+        let created = matches!(
+            env::promise_result(0),
+            PromiseResult::Successful(result) if near_sdk::serde_json::from_slice::<bool>(&result).unwrap_or(false)
+        );
+        let receiver_id = if created {
+            new_account_id
+        } else {
+            drop.creator_id.clone()
+        };
+
+        let initial_storage_usage = env::storage_usage();
+        self.internal_release_drop_token(&drop, &receiver_id);
+        let freed_storage = freed_storage + initial_storage_usage.saturating_sub(env::storage_usage());
+        self.internal_close_drop(drop, freed_storage, !created);
+        created
+    }
+
+    // TAKE BACK THE TOKEN OF AN EXPIRED DROP
+    #[payable]
+    pub fn reclaim_drop(&mut self, public_key: PublicKey) {
+        assert_one_yocto();
+        let drop = self.drops.get(&public_key).expect("NO DROP FOR THIS KEY");
+        assert_eq!(
+            env::predecessor_account_id(),
+            drop.creator_id,
+            "ONLY THE DROP CREATOR CAN RECLAIM IT"
+        );
+        assert!(
+            env::block_timestamp() > drop.expires_at,
+            "DROP HAS NOT EXPIRED YET"
+        );
+        let initial_storage_usage = env::storage_usage();
+        self.drops.remove(&public_key);
+
+        self.internal_release_drop_token(&drop, &drop.creator_id);
+        let freed_storage = initial_storage_usage.saturating_sub(env::storage_usage());
+        self.internal_close_drop(drop, freed_storage, true);
+    }
+
+    pub fn get_drop(&self, public_key: PublicKey) -> Option<Drop> {
+        self.drops.get(&public_key)
+    }
+}
+
+impl Contract {
+    //remove the drop of the key the transaction was signed with
+    pub(crate) fn internal_take_drop(&mut self) -> Drop {
+        assert_eq!(
+            env::predecessor_account_id(),
+            env::current_account_id(),
+            "DROPS ARE CLAIMED WITH THEIR KEY"
+        );
+        self.assert_not_paused(PauseFeature::Transfers);
+        let public_key = env::signer_account_pk();
+        let drop = self.drops.get(&public_key).expect("NO DROP FOR THIS KEY");
+        assert!(
+            env::block_timestamp() <= drop.expires_at,
+            "DROP HAS EXPIRED"
+        );
+        self.drops.remove(&public_key);
+        drop
+    }
+
+    /*
+        hand the escrowed token of a drop over. It never panics so that the drop can always be closed:
+        a token that isn't held by the contract anymore or can't be transferred is left where it is.
+    */
+    pub(crate) fn internal_release_drop_token(&mut self, drop: &Drop, receiver_id: &AccountId) {
+        let releasable = self
+            .internal_get_token(&drop.token_id)
+            .filter(|token| {
+                token.owner_id == env::current_account_id()
+                    && self.internal_is_transferable(&drop.token_id, token)
+            })
+            .is_some();
+        if releasable {
+            self.internal_transfer(&env::current_account_id(), receiver_id, &drop.token_id, None, None);
+        }
+    }
+
+    //tokens held by the contract are in a drop escrow, they can't be expired or bound before the drop is closed
+    pub(crate) fn assert_not_in_drop(&self, token: &Token) {
+        assert_ne!(token.owner_id, env::current_account_id(), "TOKEN IS HELD IN A DROP");
+    }
+
+    //delete the key of a drop that was handed over and refund the freed storage and, unless it was spent, the funding
+    pub(crate) fn internal_close_drop(&mut self, drop: Drop, freed_storage: u64, refund_funding: bool) {
+        Promise::new(env::current_account_id()).delete_key(drop.public_key);
+
+        let storage_refund = env::storage_byte_cost() * Balance::from(freed_storage);
+        let refund = if refund_funding {
+            drop.funding.0 + storage_refund
+        } else {
+            storage_refund
+        };
+        if refund > 0 {
+            Promise::new(drop.creator_id).transfer(refund);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::mock::VmAction;
+    use near_sdk::test_utils::{get_created_receipts, VMContextBuilder};
+    use near_sdk::{testing_env, RuntimeFeesConfig, VMConfig};
+
+    const CLAIM_GAS: Gas = Gas(30_000_000_000_000);
+
+    fn drop_key() -> PublicKey {
+        "ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp".parse().unwrap()
+    }
+
+    //alice.near drops token 1 with 1 NEAR of funding, expiring at 1_000
+    fn contract_with_drop() -> (Contract, Drop) {
+        let mut contract = new_contract();
+        insert_token(&mut contract, 1, "alice.near");
+        set_context("alice.near", 2 * ONE_NEAR, 0);
+        let drop = contract.create_drop(1, drop_key(), 1_000);
+        (contract, drop)
+    }
+
+    //the next call is signed with the drop key
+    fn claim_context(prepaid_gas: Gas) -> VMContextBuilder {
+        let mut context = VMContextBuilder::new();
+        context
+            .current_account_id(account("contract.near"))
+            .predecessor_account_id(account("contract.near"))
+            .signer_account_pk(drop_key())
+            .prepaid_gas(prepaid_gas);
+        context
+    }
+
+    //what was sent to an account and whether the drop key was deleted
+    fn refund_and_key_deletion(receiver_id: &str) -> (Balance, bool) {
+        let receipts = get_created_receipts();
+        let refund = receipts
+            .iter()
+            .filter(|receipt| receipt.receiver_id == account(receiver_id))
+            .flat_map(|receipt| receipt.actions.iter())
+            .map(|action| match action {
+                VmAction::Transfer { deposit } => *deposit,
+                _ => 0,
+            })
+            .sum();
+        let key_deleted = receipts.iter().flat_map(|receipt| receipt.actions.iter()).any(
+            |action| matches!(action, VmAction::DeleteKey { public_key } if *public_key == drop_key()),
+        );
+        (refund, key_deleted)
+    }
+
+    #[test]
+    fn reclaim_after_failed_claims_keeps_the_key_allowance() {
+        let (mut contract, drop) = contract_with_drop();
+        //the creator holds the key and burns its allowance with claims that can only fail
+        testing_env!(claim_context(CLAIM_GAS).block_timestamp(1_001).build());
+        let claim = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.claim_drop(account("alice.near"))
+        }));
+        assert!(claim.is_err());
+
+        set_context("alice.near", 1, 1_001);
+        contract.reclaim_drop(drop_key());
+
+        //the storage and the funding come back, the allowance paid for the failed claims
+        let (refund, key_deleted) = refund_and_key_deletion("alice.near");
+        assert!(key_deleted);
+        assert!(refund > drop.funding.0 && refund < drop.funding.0 + DROP_KEY_ALLOWANCE);
+        assert_eq!(contract.internal_get_token(&1).unwrap().owner_id, account("alice.near"));
+    }
+
+    #[test]
+    fn claim_refunds_the_storage_and_the_funding() {
+        let (mut contract, drop) = contract_with_drop();
+        testing_env!(claim_context(CLAIM_GAS).build());
+        contract.claim_drop(account("bob.near"));
+
+        let (refund, key_deleted) = refund_and_key_deletion("alice.near");
+        assert!(key_deleted);
+        assert!(refund > drop.funding.0 && refund < drop.funding.0 + DROP_KEY_ALLOWANCE);
+        assert_eq!(contract.internal_get_token(&1).unwrap().owner_id, account("bob.near"));
+    }
+
+    #[test]
+    fn failed_account_creation_returns_the_token_and_deletes_the_key() {
+        let (mut contract, drop) = contract_with_drop();
+        //the account creation and its callback get their own gas
+        testing_env!(claim_context(Gas(100_000_000_000_000)).build());
+        contract.create_account_and_claim(account("new.near"), drop_key());
+        assert!(contract.get_drop(drop_key()).is_none());
+
+        testing_env!(
+            claim_context(CLAIM_GAS).build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed]
+        );
+        let funding = drop.funding.0;
+        assert!(!contract.resolve_create_account(drop, account("new.near"), 0));
+
+        let (refund, key_deleted) = refund_and_key_deletion("alice.near");
+        assert!(key_deleted);
+        assert!(refund > funding);
+        assert_eq!(contract.internal_get_token(&1).unwrap().owner_id, account("alice.near"));
+        assert!(contract.get_drop(drop_key()).is_none());
+    }
+
+    #[test]
+    fn drop_is_closed_even_if_its_token_cannot_be_handed_over() {
+        let (mut contract, drop) = contract_with_drop();
+        contract.non_transferable_tokens.insert(&1);
+
+        testing_env!(
+            claim_context(CLAIM_GAS).build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(b"true".to_vec())]
+        );
+        assert!(contract.resolve_create_account(drop, account("new.near"), 0));
+        let (_, key_deleted) = refund_and_key_deletion("alice.near");
+        assert!(key_deleted);
+    }
+
+    #[test]
+    #[should_panic(expected = "TOKEN IS HELD IN A DROP")]
+    fn escrowed_token_cannot_be_bound() {
+        let (mut contract, _) = contract_with_drop();
+        set_context("owner.near", ONE_NEAR, 0);
+        contract.set_token_transferable(1, false);
+    }
+
+    #[test]
+    fn escrowed_token_is_not_expired() {
+        let (mut contract, _) = contract_with_drop();
+        contract.validity_policy.on_expiry = ExpiryAction::Burn;
+        let mut metadata = empty_metadata();
+        metadata.expires_at = Some(1);
+        contract.internal_insert_token_metadata(&1, &metadata);
+
+        set_context("bob.near", 0, 2_000_000_000);
+        assert!(contract.expire_tokens(vec![1]).is_empty());
+        assert!(contract.internal_get_token(&1).is_some());
+    }
+}
@@ -0,0 +1,273 @@
+use crate::*;
+
+//how the price of a dutch listing goes down from the start price to the floor price
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum PriceCurve {
+    //the price goes down continuously over the listing window
+    Linear,
+    //the price drops once every `step_duration` nanoseconds
+    Stepped { step_duration: u64 },
+}
+
+//a descending price listing. The price is computed from the block timestamp when the token is bought.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DutchListing {
+    pub token_id: TokenId,
+    //owner of the token when the listing was created (the seller)
+    pub owner_id: AccountId,
+    pub start_price: U128,
+    pub floor_price: U128,
+    //block timestamps in nanoseconds between which the price goes down
+    pub starts_at: u64,
+    pub ends_at: u64,
+    pub curve: PriceCurve,
+}
+
+impl DutchListing {
+    //the price of the listing at the given block timestamp
+    pub fn price_at(&self, timestamp: u64) -> Balance {
+        if timestamp <= self.starts_at {
+            return self.start_price.0;
+        }
+        if timestamp >= self.ends_at {
+            return self.floor_price.0;
+        }
+
+        let duration = (self.ends_at - self.starts_at) as u128;
+        let elapsed = match self.curve {
+            PriceCurve::Linear => timestamp - self.starts_at,
+            //only count the steps that are fully elapsed
+            PriceCurve::Stepped { step_duration } => {
+                (timestamp - self.starts_at) / step_duration * step_duration
+            }
+        } as u128;
+
+        //the full drop times the elapsed nanoseconds can overflow for big prices, fall back to dividing first
+        let total_drop = self.start_price.0 - self.floor_price.0;
+        let drop = total_drop
+            .checked_mul(elapsed)
+            .map(|value| value / duration)
+            .unwrap_or_else(|| total_drop / duration * elapsed);
+
+        self.start_price.0 - drop
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    // LIST A TOKEN WITH A PRICE DECAYING FROM start_price TO floor_price --- BOUGHT THROUGH nft_buy
+    #[payable]
+    pub fn create_dutch_listing(
+        &mut self,
+        token_id: TokenId,
+        start_price: U128,
+        floor_price: U128,
+        starts_at: Option<u64>,
+        ends_at: u64,
+        curve: PriceCurve,
+    ) -> DutchListing {
+        assert_at_least_one_yocto();
+        let initial_storage_usage = env::storage_usage();
+
+        let mut token_data = self.internal_get_token(&token_id).expect("TOKEN DOESNT EXIST");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_data.owner_id,
+            "ONLY TOKEN OWNER CAN CREATE A DUTCH LISTING"
+        );
+        self.assert_not_on_auction(&token_id);
+        self.assert_not_expired_for_sale(&token_id);
+        //bound and rented tokens can't be handed to a buyer
+        self.assert_transferable(&token_id, &token_data);
+        self.assert_not_rented(&token_id);
+
+        //if no start was passed in, the price starts going down right away
+        let starts_at = starts_at.unwrap_or_else(env::block_timestamp);
+        assert!(ends_at > starts_at, "LISTING END MUST BE AFTER ITS START");
+        assert!(
+            start_price.0 >= floor_price.0,
+            "START PRICE CANNOT BE LOWER THAN FLOOR PRICE"
+        );
+        if let PriceCurve::Stepped { step_duration } = curve {
+            assert!(step_duration > 0, "STEP DURATION MUST BE > 0");
+        }
+
+        //the fixed price is replaced by the dutch listing
+        token_data.price = None;
+        token_data.ft_token_id = None;
+        self.internal_insert_token(&token_id, &token_data);
+
+        let listing = DutchListing {
+            token_id,
+            owner_id: token_data.owner_id,
+            start_price,
+            floor_price,
+            starts_at,
+            ends_at,
+            curve,
+        };
+        self.dutch_listings.insert(&token_id, &listing);
+        log_nft_list(vec![NftListLog {
+            owner_id: listing.owner_id.to_string(),
+            token_id: Some(token_id.to_string()),
+            series_id: None,
+            price: Some(start_price),
+            ft_token_id: None,
+        }]);
+
+        //refund any excess storage if the user attached too much. Whatever wasn't attached is taken from the storage balance.
+        let required_storage_in_bytes = env::storage_usage() - initial_storage_usage;
+        self.internal_refund_deposit(required_storage_in_bytes);
+
+        listing
+    }
+
+    #[payable]
+    pub fn cancel_dutch_listing(&mut self, token_id: TokenId) {
+        assert_one_yocto();
+        let listing = self
+            .dutch_listings
+            .get(&token_id)
+            .expect("NO DUTCH LISTING FOR TOKEN");
+        assert_eq!(
+            env::predecessor_account_id(),
+            listing.owner_id,
+            "ONLY LISTING OWNER CAN CANCEL"
+        );
+        self.internal_remove_dutch_listing(&token_id);
+        log_nft_list(vec![NftListLog {
+            owner_id: listing.owner_id.to_string(),
+            token_id: Some(token_id.to_string()),
+            series_id: None,
+            price: None,
+            ft_token_id: None,
+        }]);
+    }
+
+    //get the price a dutch listed token can be bought for right now
+    pub fn get_dutch_price(&self, token_id: TokenId) -> U128 {
+        let listing = self
+            .dutch_listings
+            .get(&token_id)
+            .expect("NO DUTCH LISTING FOR TOKEN");
+        U128(listing.price_at(env::block_timestamp()))
+    }
+
+    pub fn get_dutch_listing(&self, token_id: TokenId) -> Option<DutchListing> {
+        self.dutch_listings.get(&token_id)
+    }
+
+    //Query for the dutch listings using pagination
+    pub fn get_dutch_listings(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<DutchListing> {
+        //where to start pagination - if we have a from_index, we'll use that - otherwise start from 0 index
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        self.dutch_listings.values()
+            //skip to the index we specified in the start variable
+            .skip(start as usize)
+            //take the first "limit" elements in the vector. If we didn't specify a limit, use 50
+            .take(limit.unwrap_or(50) as usize)
+            .collect()
+    }
+}
+
+impl Contract {
+    //remove the dutch listing of a token and give its owner back the storage it paid for
+    pub(crate) fn internal_remove_dutch_listing(&mut self, token_id: &TokenId) -> Option<DutchListing> {
+        let initial_storage_usage = env::storage_usage();
+        let listing = self.dutch_listings.remove(token_id)?;
+        let storage_released = initial_storage_usage - env::storage_usage();
+        Promise::new(listing.owner_id.clone()).transfer(Balance::from(storage_released) * env::storage_byte_cost());
+        Some(listing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nft_core::NonFungibleTokenCore;
+    use crate::test_utils::*;
+    use near_sdk::mock::VmAction;
+    use near_sdk::test_utils::get_created_receipts;
+
+    fn listing(curve: PriceCurve) -> DutchListing {
+        DutchListing {
+            token_id: 1,
+            owner_id: "owner.near".parse().unwrap(),
+            start_price: U128(1_000),
+            floor_price: U128(200),
+            starts_at: 100,
+            ends_at: 500,
+            curve,
+        }
+    }
+
+    #[test]
+    fn linear_price_decays_to_floor() {
+        let listing = listing(PriceCurve::Linear);
+        assert_eq!(listing.price_at(0), 1_000);
+        assert_eq!(listing.price_at(300), 600);
+        assert_eq!(listing.price_at(500), 200);
+        assert_eq!(listing.price_at(10_000), 200);
+    }
+
+    #[test]
+    fn stepped_price_only_drops_on_full_steps() {
+        let listing = listing(PriceCurve::Stepped { step_duration: 100 });
+        assert_eq!(listing.price_at(199), 1_000);
+        assert_eq!(listing.price_at(200), 800);
+        assert_eq!(listing.price_at(499), 400);
+        assert_eq!(listing.price_at(500), 200);
+    }
+
+    #[test]
+    fn big_prices_do_not_overflow() {
+        let mut listing = listing(PriceCurve::Linear);
+        listing.start_price = U128(u128::MAX / 2);
+        listing.floor_price = U128(0);
+        listing.ends_at = u64::MAX;
+        assert!(listing.price_at(u64::MAX / 2) < u128::MAX / 2);
+    }
+
+    //what was sent to an account by the last call
+    fn transferred_to(receiver_id: &str) -> Balance {
+        get_created_receipts()
+            .iter()
+            .filter(|receipt| receipt.receiver_id == account(receiver_id))
+            .flat_map(|receipt| receipt.actions.iter())
+            .map(|action| match action {
+                VmAction::Transfer { deposit } => *deposit,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    fn contract_with_dutch_listing() -> (Contract, u64) {
+        let mut contract = new_contract();
+        insert_token(&mut contract, 1, "alice.near");
+        set_context("alice.near", ONE_NEAR, 0);
+        let storage_before = env::storage_usage();
+        contract.create_dutch_listing(1, U128(1_000), U128(200), None, 500, PriceCurve::Linear);
+        (contract, env::storage_usage() - storage_before)
+    }
+
+    #[test]
+    fn cancel_refunds_the_listing_storage() {
+        let (mut contract, listing_bytes) = contract_with_dutch_listing();
+        set_context("alice.near", 1, 0);
+        contract.cancel_dutch_listing(1);
+        assert!(contract.get_dutch_listing(1).is_none());
+        assert_eq!(transferred_to("alice.near"), Balance::from(listing_bytes) * env::storage_byte_cost());
+    }
+
+    #[test]
+    fn transfer_refunds_the_listing_storage_to_the_seller() {
+        let (mut contract, listing_bytes) = contract_with_dutch_listing();
+        set_context("alice.near", 1, 0);
+        contract.nft_transfer(account("bob.near"), 1, None, None);
+        assert!(contract.get_dutch_listing(1).is_none());
+        assert_eq!(transferred_to("alice.near"), Balance::from(listing_bytes) * env::storage_byte_cost());
+    }
+}
@@ -1,10 +1,26 @@
+use std::collections::HashMap;
 use std::fmt;
 
 
+use near_sdk::env;
+use near_sdk::json_types::U128;
 use near_sdk::serde::{Deserialize, Serialize};
 
+use crate::{
+    ExpiryAction, PauseFeature, Role, TokenId, BLOCKPERKS_EVENT_VERSION, BLOCKPERKS_STANDARD_NAME,
+    NFT_METADATA_SPEC, NFT_STANDARD_NAME,
+};
+
+//longest event line we log, big operations are split into lines that indexers can parse on their own.
+//Splitting doesn't raise the 16 KiB a receipt can log in total (max_total_log_length): that is kept by
+//the batch caps of the operations, e.g. 125 IDs of at most 23 bytes for batch_mint and unique_mint.
+const MAX_LOG_LENGTH: usize = 4 * 1024;
+
 /// Enum that represents the data type of the EventLog.
-/// The enum can either be an NftMint or an NftTransfer.
+/// The NEP-171 variants are logged with the "nep171" standard: version 1.0.0 for mints, transfers
+/// and burns, 1.1.0 for contract metadata updates and 1.2.0 for token metadata updates.
+/// Every other variant (listings, sales, approvals, fees, auctions, roles, ownership, pause, series,
+/// recovery, user, expiry and redemption) is logged with the "blockperks" standard.
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(tag = "event", content = "data")]
 #[serde(rename_all = "snake_case")]
@@ -15,6 +31,30 @@ pub enum EventLogVariant {
     NftTransfer(Vec<NftTransferLog>),
     NftBurn(Vec<NftburnLog>),
     NftUniqueMint(Vec<NftUniqueMintLog>),
+    AuctionCreate(Vec<AuctionCreateLog>),
+    AuctionBid(Vec<AuctionBidLog>),
+    AuctionSettle(Vec<AuctionSettleLog>),
+    AuctionCancel(Vec<AuctionCancelLog>),
+    RoleGrant(Vec<RoleLog>),
+    RoleRevoke(Vec<RoleLog>),
+    OwnershipPropose(Vec<OwnershipProposalLog>),
+    OwnershipCancel(Vec<OwnershipProposalLog>),
+    OwnershipAccept(Vec<OwnershipTransferLog>),
+    TreasuryUpdate(Vec<TreasuryUpdateLog>),
+    ContractPause(Vec<PauseLog>),
+    ContractUnpause(Vec<PauseLog>),
+    SeriesCreate(Vec<SeriesCreateLog>),
+    TokenRecover(Vec<TokenRecoverLog>),
+    UserUpdate(Vec<UserUpdateLog>),
+    TokenExpire(Vec<TokenExpireLog>),
+    NftRedeem(Vec<NftRedeemLog>),
+    ContractMetadataUpdate(Vec<ContractMetadataUpdateLog>),
+    NftMetadataUpdate(Vec<NftMetadataUpdateLog>),
+    NftList(Vec<NftListLog>),
+    NftSale(Vec<NftSaleLog>),
+    NftApprove(Vec<NftApproveLog>),
+    NftRevoke(Vec<NftRevokeLog>),
+    FeeUpdate(Vec<FeeUpdateLog>),
 }
 
 /// Interface to capture data about an event
@@ -43,6 +83,105 @@ impl fmt::Display for EventLog {
     }
 }
 
+impl EventLog {
+    /// Log one event for a whole list of token IDs. When the event would be longer than
+    /// `MAX_LOG_LENGTH`, the token IDs are split across as many events as needed.
+    /// All of them still count towards the total log limit of the receipt.
+    ///
+    /// Arguments
+    /// * `token_ids`: every token ID of the operation
+    /// * `event`: builds the event for a chunk of token IDs
+    pub fn log_chunked<F>(standard: &str, version: &str, token_ids: &[TokenId], event: F)
+    where
+        F: Fn(Vec<String>) -> EventLogVariant,
+    {
+        let log = |token_ids: Vec<String>| EventLog {
+            standard: standard.to_string(),
+            version: version.to_string(),
+            event: event(token_ids),
+        };
+        //length of the event without any token ID, every ID adds its quoted self and a comma
+        let empty_length = log(vec![]).to_string().len();
+
+        let mut chunk: Vec<String> = Vec::new();
+        let mut length = empty_length;
+        for token_id in token_ids {
+            let token_id = token_id.to_string();
+            if !chunk.is_empty() && length + token_id.len() + 3 > MAX_LOG_LENGTH {
+                env::log_str(&log(std::mem::take(&mut chunk)).to_string());
+                length = empty_length;
+            }
+            length += token_id.len() + 2 + usize::from(!chunk.is_empty());
+            chunk.push(token_id);
+        }
+        if !chunk.is_empty() {
+            env::log_str(&log(chunk).to_string());
+        }
+    }
+}
+
+//log the tokens minted to an owner by one operation
+pub(crate) fn log_nft_mint(owner_id: &str, token_ids: &[TokenId], memo: Option<String>) {
+    EventLog::log_chunked(NFT_STANDARD_NAME, NFT_METADATA_SPEC, token_ids, |token_ids| {
+        EventLogVariant::NftMint(vec![NftMintLog {
+            owner_id: owner_id.to_string(),
+            token_ids,
+            memo: memo.clone(),
+        }])
+    });
+}
+
+//log the tokens minted with their own metadata to an owner by one operation
+pub(crate) fn log_nft_unique_mint(owner_id: &str, token_ids: &[TokenId], memo: Option<String>) {
+    EventLog::log_chunked(NFT_STANDARD_NAME, NFT_METADATA_SPEC, token_ids, |token_ids| {
+        EventLogVariant::NftUniqueMint(vec![NftUniqueMintLog {
+            owner_id: owner_id.to_string(),
+            token_ids,
+            memo: memo.clone(),
+        }])
+    });
+}
+
+//log the tokens of an owner burned by one operation
+pub(crate) fn log_nft_burn(owner_id: &str, token_ids: &[TokenId], memo: Option<String>) {
+    EventLog::log_chunked(NFT_STANDARD_NAME, NFT_METADATA_SPEC, token_ids, |token_ids| {
+        EventLogVariant::NftBurn(vec![NftburnLog {
+            owner_id: owner_id.to_string(),
+            token_ids,
+            memo: memo.clone(),
+        }])
+    });
+}
+
+//log the listings changed by one operation
+pub(crate) fn log_nft_list(list_logs: Vec<NftListLog>) {
+    let nft_list_log: EventLog = EventLog {
+        standard: BLOCKPERKS_STANDARD_NAME.to_string(),
+        version: BLOCKPERKS_EVENT_VERSION.to_string(),
+        event: EventLogVariant::NftList(list_logs),
+    };
+    env::log_str(&nft_list_log.to_string());
+}
+
+//log the tokens moved from one owner to another by one operation
+pub(crate) fn log_nft_transfer(
+    authorized_id: Option<String>,
+    old_owner_id: &str,
+    new_owner_id: &str,
+    token_ids: &[TokenId],
+    memo: Option<String>,
+) {
+    EventLog::log_chunked(NFT_STANDARD_NAME, NFT_METADATA_SPEC, token_ids, |token_ids| {
+        EventLogVariant::NftTransfer(vec![NftTransferLog {
+            authorized_id: authorized_id.clone(),
+            old_owner_id: old_owner_id.to_string(),
+            new_owner_id: new_owner_id.to_string(),
+            token_ids,
+            memo: memo.clone(),
+        }])
+    });
+}
+
 /// An event log to capture token minting
 ///
 /// Arguments
@@ -101,6 +240,324 @@ pub struct NftburnLog {
     pub memo: Option<String>,
 }
 
+/// An event log to capture the opening of an English auction
+///
+/// Arguments
+/// * `owner_id`: "owner.near"
+/// * `token_id`: "12"
+/// * `reserve_price`: lowest accepted first bid in yoctoNEAR
+/// * `min_bid_increment`: how much every next bid has to add in yoctoNEAR
+/// * `ends_at`: block timestamp (nanoseconds) after which no bids are accepted
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuctionCreateLog {
+    pub owner_id: String,
+    pub token_id: String,
+    pub reserve_price: U128,
+    pub min_bid_increment: U128,
+    pub ends_at: u64,
+}
+
+/// An event log to capture a bid on an auction
+///
+/// Arguments
+/// * `bidder_id`: "bidder.near"
+/// * `token_id`: "12"
+/// * `amount`: bid in yoctoNEAR
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuctionBidLog {
+    pub bidder_id: String,
+    pub token_id: String,
+    pub amount: U128,
+}
+
+/// An event log to capture the settlement of an auction
+///
+/// Arguments
+/// * `owner_id`: "owner.near"
+/// * `token_id`: "12"
+/// * `winner_id`: winning bidder, none if nobody bid
+/// * `amount`: winning bid in yoctoNEAR, none if nobody bid
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuctionSettleLog {
+    pub owner_id: String,
+    pub token_id: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub winner_id: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount: Option<U128>,
+}
+
+/// An event log to capture an auction cancelled by its owner before any bid
+///
+/// Arguments
+/// * `owner_id`: "owner.near"
+/// * `token_id`: "12"
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuctionCancelLog {
+    pub owner_id: String,
+    pub token_id: String,
+}
+
+/// An event log to capture a role being granted or revoked
+///
+/// Arguments
+/// * `role`: "Minter"
+/// * `account_id`: account the role was granted to or revoked from
+/// * `sender_id`: admin that made the change (the account itself when a role is renounced)
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RoleLog {
+    pub role: Role,
+    pub account_id: String,
+    pub sender_id: String,
+}
+
+/// An event log to capture an ownership proposal being made or cancelled
+///
+/// Arguments
+/// * `owner_id`: current owner of the contract
+/// * `proposed_owner_id`: account that can accept the ownership
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OwnershipProposalLog {
+    pub owner_id: String,
+    pub proposed_owner_id: String,
+}
+
+/// An event log to capture the ownership of the contract being accepted
+///
+/// Arguments
+/// * `old_owner_id`: previous owner of the contract
+/// * `new_owner_id`: new owner of the contract
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OwnershipTransferLog {
+    pub old_owner_id: String,
+    pub new_owner_id: String,
+}
+
+/// An event log to capture the treasury account being changed
+///
+/// Arguments
+/// * `old_treasury_id`: account that received the transaction fees until now
+/// * `new_treasury_id`: account receiving the transaction fees from now on
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TreasuryUpdateLog {
+    pub old_treasury_id: String,
+    pub new_treasury_id: String,
+}
+
+/// An event log to capture a part of the contract being paused or unpaused
+///
+/// Arguments
+/// * `feature`: "Trading"
+/// * `sender_id`: account that paused or unpaused it
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PauseLog {
+    pub feature: PauseFeature,
+    pub sender_id: String,
+}
+
+/// An event log to capture the creation of a series
+///
+/// Arguments
+/// * `creator_id`: "creator.near"
+/// * `series_id`: "3"
+/// * `max_supply`: how many copies can be minted, unlimited if none
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SeriesCreateLog {
+    pub creator_id: String,
+    pub series_id: String,
+    pub max_supply: Option<u64>,
+}
+
+/// An event log to capture an admin reassigning non transferable tokens to a new account
+///
+/// Arguments
+/// * `authorized_id`: admin who recovered the tokens
+/// * `old_owner_id`: "lost.near"
+/// * `new_owner_id`: "new.near"
+/// * `token_ids`: ["1", "12345abc"]
+/// * `memo`: optional message
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenRecoverLog {
+    pub authorized_id: String,
+    pub old_owner_id: String,
+    pub new_owner_id: String,
+    pub token_ids: Vec<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+/// An event log to capture an account being allowed to use a token it doesn't own
+///
+/// Arguments
+/// * `token_id`: "1"
+/// * `user_id`: "renter.near"
+/// * `expires_at`: block timestamp in nanoseconds at which the right to use the token ends
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct UserUpdateLog {
+    pub token_id: String,
+    pub user_id: String,
+    pub expires_at: u64,
+}
+
+/// An event log to capture expired tokens being marked or burned
+///
+/// Arguments
+/// * `token_ids`: ["1", "12345abc"]
+/// * `action`: "Mark" or "Burn"
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenExpireLog {
+    pub token_ids: Vec<String>,
+    pub action: ExpiryAction,
+}
+
+/// An event log to capture a perk being redeemed with a merchant
+///
+/// Arguments
+/// * `owner_id`: "holder.near"
+/// * `merchant_id`: "merchant.near"
+/// * `token_ids`: ["1", "12345abc"]
+/// * `memo`: optional message
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftRedeemLog {
+    pub owner_id: String,
+    pub merchant_id: String,
+    pub token_ids: Vec<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+/// An event log to capture an update of the contract metadata (NEP-171 1.1.0)
+///
+/// Arguments
+/// * `memo`: optional message
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ContractMetadataUpdateLog {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+/// An event log to capture an update of token metadata (NEP-171 1.2.0)
+///
+/// Arguments
+/// * `token_ids`: ["1", "12345abc"]
+/// * `memo`: optional message
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftMetadataUpdateLog {
+    pub token_ids: Vec<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+/// An event log to capture a token or a lazy series being listed, repriced or delisted
+///
+/// Arguments
+/// * `owner_id`: "owner.near"
+/// * `token_id`: "12", none for a lazy series
+/// * `series_id`: "3", only for a lazy series
+/// * `price`: fixed price or start price of a dutch listing, none when the token is delisted
+/// * `ft_token_id`: fungible token the price is in, NEAR if none
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftListLog {
+    pub owner_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub series_id: Option<String>,
+    pub price: Option<U128>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ft_token_id: Option<String>,
+}
+
+/// An event log to capture a sale and how its price was paid out
+///
+/// Arguments
+/// * `seller_id`: "seller.near"
+/// * `buyer_id`: "buyer.near"
+/// * `token_id`: "12"
+/// * `price`: what the buyer paid
+/// * `ft_token_id`: fungible token the price was paid in, NEAR if none
+/// * `payout`: what every royalty, split payment, the treasury and the seller received
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftSaleLog {
+    pub seller_id: String,
+    pub buyer_id: String,
+    pub token_id: String,
+    pub price: U128,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ft_token_id: Option<String>,
+
+    pub payout: HashMap<String, U128>,
+}
+
+/// An event log to capture an account being approved on a token
+///
+/// Arguments
+/// * `owner_id`: "owner.near"
+/// * `token_id`: "12"
+/// * `account_id`: "market.near"
+/// * `approval_id`: 3
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftApproveLog {
+    pub owner_id: String,
+    pub token_id: String,
+    pub account_id: String,
+    pub approval_id: u64,
+}
+
+/// An event log to capture approvals being revoked
+///
+/// Arguments
+/// * `owner_id`: "owner.near"
+/// * `token_id`: "12"
+/// * `account_ids`: ["market.near"], every approved account for nft_revoke_all
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftRevokeLog {
+    pub owner_id: String,
+    pub token_id: String,
+    pub account_ids: Vec<String>,
+}
+
+/// An event log to capture a change of the transaction fee
+///
+/// Arguments
+/// * `old_fee`: previous fee in basis points
+/// * `new_fee`: fee in basis points from now on
+/// * `sender_id`: "feemanager.near"
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FeeUpdateLog {
+    pub old_fee: u16,
+    pub new_fee: u16,
+    pub sender_id: String,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -158,4 +615,71 @@ mod tests {
         };
         assert_eq!(expected, log.to_string());
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn blockperks_format_auction_bid() {
+        let expected = r#"EVENT_JSON:{"standard":"blockperks","version":"1.0.0","event":"auction_bid","data":[{"bidder_id":"user2.near","token_id":"12","amount":"1000"}]}"#;
+        let log = EventLog {
+            standard: "blockperks".to_string(),
+            version: "1.0.0".to_string(),
+            event: EventLogVariant::AuctionBid(vec![AuctionBidLog {
+                bidder_id: "user2.near".to_string(),
+                token_id: "12".to_string(),
+                amount: U128(1000),
+            }]),
+        };
+        assert_eq!(expected, log.to_string());
+    }
+
+    #[test]
+    fn nep_format_metadata_update() {
+        let expected = r#"EVENT_JSON:{"standard":"nep171","version":"1.2.0","event":"nft_metadata_update","data":[{"token_ids":["1","2"]}]}"#;
+        let log = EventLog {
+            standard: "nep171".to_string(),
+            version: "1.2.0".to_string(),
+            event: EventLogVariant::NftMetadataUpdate(vec![NftMetadataUpdateLog {
+                token_ids: vec!["1".to_string(), "2".to_string()],
+                memo: None,
+            }]),
+        };
+        assert_eq!(expected, log.to_string());
+    }
+
+    #[test]
+    fn blockperks_format_sale() {
+        let expected = r#"EVENT_JSON:{"standard":"blockperks","version":"1.0.0","event":"nft_sale","data":[{"seller_id":"user1.near","buyer_id":"user2.near","token_id":"12","price":"1000","payout":{"user1.near":"1000"}}]}"#;
+        let log = EventLog {
+            standard: "blockperks".to_string(),
+            version: "1.0.0".to_string(),
+            event: EventLogVariant::NftSale(vec![NftSaleLog {
+                seller_id: "user1.near".to_string(),
+                buyer_id: "user2.near".to_string(),
+                token_id: "12".to_string(),
+                price: U128(1000),
+                ft_token_id: None,
+                payout: HashMap::from([("user1.near".to_string(), U128(1000))]),
+            }]),
+        };
+        assert_eq!(expected, log.to_string());
+    }
+
+    #[test]
+    fn chunked_log_fits_the_log_limit() {
+        near_sdk::testing_env!(near_sdk::test_utils::VMContextBuilder::new().build());
+        let token_ids: Vec<TokenId> = (u64::MAX - 500..u64::MAX).collect();
+        log_nft_mint("user1.near", &token_ids, None);
+
+        let logs = near_sdk::test_utils::get_logs();
+        assert!(logs.len() > 1);
+        let mut logged = Vec::new();
+        for log in logs {
+            assert!(log.len() <= MAX_LOG_LENGTH);
+            let json: near_sdk::serde_json::Value =
+                near_sdk::serde_json::from_str(log.strip_prefix("EVENT_JSON:").unwrap()).unwrap();
+            for token_id in json["data"][0]["token_ids"].as_array().unwrap() {
+                logged.push(token_id.as_str().unwrap().parse::<TokenId>().unwrap());
+            }
+        }
+        assert_eq!(logged, token_ids);
+    }
+}
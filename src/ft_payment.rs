@@ -0,0 +1,293 @@
+use crate::*;
+use near_sdk::serde_json::json;
+use near_sdk::{Gas, PromiseResult};
+
+const GAS_FOR_FT_TRANSFER: Gas = Gas(5_000_000_000_000);
+const GAS_FOR_RESOLVE_FT_PAYOUT: Gas = Gas(10_000_000_000_000);
+const GAS_FOR_RESOLVE_FT_WITHDRAW: Gas = Gas(5_000_000_000_000);
+
+//the msg passed to ft_transfer_call when buying a token priced in a fungible token
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FtBuyMsg {
+    pub token_id: TokenId,
+    pub memo: Option<String>,
+}
+
+pub trait FungibleTokenReceiver {
+    //called by the fungible token contract when a buyer calls ft_transfer_call on it with this contract as receiver
+    /// Returns the amount of fungible tokens that were not used and should be refunded to the buyer.
+    fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128>;
+}
+
+//NEP-141 transfer of a fungible token, it requires exactly 1 yoctoNEAR attached
+fn ft_transfer(ft_token_id: AccountId, receiver_id: &AccountId, amount: Balance, memo: Option<String>) -> Promise {
+    let args = json!({ "receiver_id": receiver_id, "amount": U128(amount), "memo": memo });
+    Promise::new(ft_token_id).function_call(
+        "ft_transfer".to_string(),
+        args.to_string().into_bytes(),
+        1,
+        GAS_FOR_FT_TRANSFER,
+    )
+}
+
+#[near_bindgen]
+impl FungibleTokenReceiver for Contract {
+    // BUY NFT WITH A FUNGIBLE TOKEN --- SAME ROYALTIES, SPLIT-PAYMENTS AND TRANSACTION FEE AS nft_buy
+    fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        //a panic here makes the fungible token contract refund the buyer
+        self.assert_not_paused(PauseFeature::Trading);
+        //the predecessor is the fungible token contract the buyer paid with
+        let ft_token_id = env::predecessor_account_id();
+        let FtBuyMsg { token_id, memo } =
+            near_sdk::serde_json::from_str(&msg).expect("INVALID MSG, EXPECTED {\"token_id\": ..}");
+
+        let token_data = self
+            .internal_get_token(&token_id)
+            .expect("TOKEN DOESNT EXIST");
+        assert_ne!(
+            sender_id, token_data.owner_id,
+            "YOU CANNOT BUY YOUR OWN NFT"
+        );
+        self.assert_not_expired_for_sale(&token_id);
+        assert_eq!(
+            token_data.ft_token_id.as_ref(),
+            Some(&ft_token_id),
+            "TOKEN IS NOT PRICED IN THIS FUNGIBLE TOKEN"
+        );
+        assert!(
+            self.accepted_ft_tokens.contains(&ft_token_id),
+            "FUNGIBLE TOKEN IS NOT ACCEPTED AS PAYMENT"
+        );
+        let price: u128 = token_data.price.expect("NOT FOR SALE");
+        assert!(amount.0 >= price, "AMOUNT IS LESS THAN PRICE :{}", price);
+
+        let initial_storage_usage = env::storage_usage();
+
+        //split the price between royalties, split payments, the treasury and the seller
+        let payout = self.internal_sale_payout(&token_data, price);
+        log_nft_sale(
+            &token_id,
+            &token_data.owner_id,
+            &sender_id,
+            price,
+            Some(&ft_token_id),
+            &payout,
+        );
+
+        //hand the token over to the buyer
+        let old_owner_id = token_data.owner_id.clone();
+        self.internal_sale_transfer(&token_id, token_data, &sender_id);
+
+        //no NEAR comes with ft_on_transfer so the buyer's storage is paid from their storage balance
+        let required_storage_in_bytes = env::storage_usage().saturating_sub(initial_storage_usage);
+        self.internal_charge_storage(&sender_id, required_storage_in_bytes, 0);
+
+        //pay every receiver in the fungible token. If a transfer fails, the amount is credited to the receiver.
+        for (receiver_id, payout_amount) in payout {
+            if payout_amount == 0 {
+                continue;
+            }
+            ft_transfer(ft_token_id.clone(), &receiver_id, payout_amount, memo.clone())
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_FT_PAYOUT)
+                    .ft_resolve_payout(ft_token_id.clone(), receiver_id, U128(payout_amount))
+            );
+        }
+
+        //if there was some memo attached, we log it.
+        if let Some(memo) = memo.as_ref() {
+            env::log_str(&format!("Memo: {}", memo));
+        }
+
+        let nft_transfer_log: EventLog = EventLog {
+            standard: NFT_STANDARD_NAME.to_string(),
+            version: NFT_METADATA_SPEC.to_string(),
+            event: EventLogVariant::NftTransfer(vec![NftTransferLog {
+                authorized_id: None,
+                old_owner_id: old_owner_id.to_string(),
+                new_owner_id: sender_id.to_string(),
+                token_ids: vec![token_id.to_string()],
+                memo: None,
+            }]),
+        };
+        env::log_str(&nft_transfer_log.to_string());
+
+        //the fungible token contract refunds whatever was sent above the price
+        PromiseOrValue::Value(U128(amount.0 - price))
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /*
+        resolves a payout ft_transfer. If it failed (e.g. the receiver isn't registered with the fungible token),
+        the amount is credited to the receiver, who withdraws it once they can receive it.
+    */
+    #[private]
+    pub fn ft_resolve_payout(&mut self, ft_token_id: AccountId, receiver_id: AccountId, amount: U128) {
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            return;
+        }
+
+        env::log_str(&format!(
+            "PAYOUT OF {} {} TO {} FAILED, CREDITED TO ITS PROCEEDS",
+            amount.0, ft_token_id, receiver_id
+        ));
+        self.internal_credit_ft_proceeds(&receiver_id, &ft_token_id, amount.0);
+    }
+
+    // WITHDRAW THE FUNGIBLE TOKEN PAYOUTS THAT COULDN'T BE SENT TO YOU --- ALL OF THEM IF NO AMOUNT IS GIVEN
+    #[payable]
+    pub fn withdraw_ft(&mut self, ft_token_id: AccountId, amount: Option<U128>) -> Promise {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let key = (account_id.clone(), ft_token_id.clone());
+        let balance = self.ft_proceeds.get(&key).unwrap_or(0);
+        let amount = amount.map(|amount| amount.0).unwrap_or(balance);
+        assert!(amount > 0, "NOTHING TO WITHDRAW");
+        assert!(amount <= balance, "BALANCE IS LESS THAN AMOUNT :{}", amount);
+
+        if balance == amount {
+            self.ft_proceeds.remove(&key);
+        } else {
+            self.ft_proceeds.insert(&key, &(balance - amount));
+        }
+
+        ft_transfer(ft_token_id.clone(), &account_id, amount, None)
+        .then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_RESOLVE_FT_WITHDRAW)
+                .ft_resolve_withdraw(ft_token_id, account_id, U128(amount))
+        )
+    }
+
+    //a withdrawal that couldn't be sent is credited back
+    #[private]
+    pub fn ft_resolve_withdraw(&mut self, ft_token_id: AccountId, account_id: AccountId, amount: U128) -> bool {
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            return true;
+        }
+        self.internal_credit_ft_proceeds(&account_id, &ft_token_id, amount.0);
+        false
+    }
+
+    pub fn ft_balance_of_proceeds(&self, account_id: AccountId, ft_token_id: AccountId) -> U128 {
+        U128(self.ft_proceeds.get(&(account_id, ft_token_id)).unwrap_or(0))
+    }
+
+    // ALLOW TOKENS TO BE PRICED IN A FUNGIBLE TOKEN (NEP-141)
+    #[payable]
+    pub fn add_accepted_ft(&mut self, ft_token_id: AccountId) {
+        assert_one_yocto();
+        self.assert_role(Role::Admin);
+        self.accepted_ft_tokens.insert(&ft_token_id);
+    }
+
+    #[payable]
+    pub fn remove_accepted_ft(&mut self, ft_token_id: AccountId) {
+        assert_one_yocto();
+        self.assert_role(Role::Admin);
+        self.accepted_ft_tokens.remove(&ft_token_id);
+    }
+
+    pub fn get_accepted_fts(&self) -> Vec<AccountId> {
+        self.accepted_ft_tokens.to_vec()
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_credit_ft_proceeds(&mut self, account_id: &AccountId, ft_token_id: &AccountId, amount: Balance) {
+        let key = (account_id.clone(), ft_token_id.clone());
+        let balance = self.ft_proceeds.get(&key).unwrap_or(0);
+        self.ft_proceeds.insert(&key, &(balance + amount));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::test_utils::{get_created_receipts, VMContextBuilder};
+    use near_sdk::{testing_env, RuntimeFeesConfig, VMConfig};
+
+    //alice.near sells token 1 for 100 usdc.near to bob.near
+    fn contract_with_ft_listing() -> Contract {
+        let mut contract = new_contract();
+        set_context("owner.near", 1, 0);
+        contract.add_accepted_ft(account("usdc.near"));
+        let mut token = insert_token(&mut contract, 1, "alice.near");
+        token.price = Some(100);
+        token.ft_token_id = Some(account("usdc.near"));
+        contract.internal_insert_token(&1, &token);
+        contract
+    }
+
+    fn resolve_with(result: PromiseResult) {
+        testing_env!(
+            VMContextBuilder::new()
+                .current_account_id(account("contract.near"))
+                .predecessor_account_id(account("contract.near"))
+                .build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![result],
+        );
+    }
+
+    #[test]
+    fn ft_sale_pays_the_seller_and_refunds_the_rest() {
+        let mut contract = contract_with_ft_listing();
+        set_context("usdc.near", 0, 0);
+        contract.storage_deposits.insert(&account("bob.near"), &ONE_NEAR);
+        let refund = contract.ft_on_transfer(
+            account("bob.near"),
+            U128(150),
+            r#"{"token_id": 1}"#.to_string(),
+        );
+        assert!(matches!(refund, PromiseOrValue::Value(U128(50))));
+        assert_eq!(contract.internal_get_token(&1).unwrap().owner_id, account("bob.near"));
+        //one ft_transfer and its resolve per payee: the seller and the treasury
+        assert_eq!(get_created_receipts().len(), 4);
+    }
+
+    #[test]
+    fn failed_payout_is_credited_to_the_receiver() {
+        let mut contract = contract_with_ft_listing();
+        resolve_with(PromiseResult::Failed);
+        contract.ft_resolve_payout(account("usdc.near"), account("alice.near"), U128(90));
+        resolve_with(PromiseResult::Failed);
+        contract.ft_resolve_payout(account("usdc.near"), account("alice.near"), U128(5));
+        assert_eq!(
+            contract.ft_balance_of_proceeds(account("alice.near"), account("usdc.near")),
+            U128(95)
+        );
+        //nothing is sent back to the buyer
+        assert!(get_created_receipts().is_empty());
+
+        set_context("alice.near", 1, 0);
+        contract.withdraw_ft(account("usdc.near"), None);
+        assert_eq!(
+            contract.ft_balance_of_proceeds(account("alice.near"), account("usdc.near")),
+            U128(0)
+        );
+        resolve_with(PromiseResult::Failed);
+        assert!(!contract.ft_resolve_withdraw(account("usdc.near"), account("alice.near"), U128(95)));
+        assert_eq!(
+            contract.ft_balance_of_proceeds(account("alice.near"), account("usdc.near")),
+            U128(95)
+        );
+    }
+}
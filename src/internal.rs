@@ -11,6 +11,33 @@ pub(crate) fn payment_splitter(percentage: u32, amount_to_pay: Balance) -> U128
     U128(percentage as u128 * amount_to_pay / 10_000u128)
 }
 
+//log a sale with what every receiver of the payout got
+pub(crate) fn log_nft_sale(
+    token_id: &TokenId,
+    seller_id: &AccountId,
+    buyer_id: &AccountId,
+    price: Balance,
+    ft_token_id: Option<&AccountId>,
+    payout: &HashMap<AccountId, Balance>,
+) {
+    let nft_sale_log: EventLog = EventLog {
+        standard: BLOCKPERKS_STANDARD_NAME.to_string(),
+        version: BLOCKPERKS_EVENT_VERSION.to_string(),
+        event: EventLogVariant::NftSale(vec![NftSaleLog {
+            seller_id: seller_id.to_string(),
+            buyer_id: buyer_id.to_string(),
+            token_id: token_id.to_string(),
+            price: U128(price),
+            ft_token_id: ft_token_id.map(|ft_token_id| ft_token_id.to_string()),
+            payout: payout
+                .iter()
+                .map(|(receiver_id, amount)| (receiver_id.to_string(), U128(*amount)))
+                .collect(),
+        }]),
+    };
+    env::log_str(&nft_sale_log.to_string());
+}
+
 //calculate how many bytes the account ID is taking up
 pub(crate) fn bytes_for_approved_account_id(account_id: &AccountId) -> u64 {
     // The extra 4 bytes are coming from Borsh serialization to store the length of the string.
@@ -66,54 +93,205 @@ pub(crate) fn assert_at_least_one_yocto() {
     )
 }
 
-//refund the initial deposit based on the amount of storage that was used up
-pub(crate) fn refund_deposit(storage_used: u64) {
-    //get how much it would cost to store the information
-    let required_cost = env::storage_byte_cost() * Balance::from(storage_used);
-    //get the attached deposit
-    let attached_deposit = env::attached_deposit();
+impl Contract {
+    //get a token, upgrading it to the current layout if it was stored with an older one
+    pub(crate) fn internal_get_token(&self, token_id: &TokenId) -> Option<Token> {
+        self.tokens_by_id.get(token_id).map(Token::from)
+    }
 
-    //make sure that the attached deposit is greater than or equal to the required cost
-    assert!(
-        required_cost <= attached_deposit,
-        "Must attach {} yoctoNEAR to cover storage",
-        required_cost,
-    );
+    //store a token with the current layout
+    pub(crate) fn internal_insert_token(&mut self, token_id: &TokenId, token: &Token) {
+        self.tokens_by_id
+            .insert(token_id, &VersionedToken::V3(token.clone()));
+    }
 
-    //get the refund amount from the attached deposit - required cost
-    let refund = attached_deposit - required_cost;
+    //get the metadata of a token, upgrading it to the current layout if it was stored with an older one.
+    //A copy of a series only stores its overrides so they're merged with the metadata of the series.
+    pub(crate) fn internal_get_token_metadata(&self, token_id: &TokenId) -> Option<TokenMetadata> {
+        let metadata = self.token_metadata_by_id.get(token_id).map(TokenMetadata::from)?;
+        let series = self
+            .internal_get_token(token_id)
+            .and_then(|token| token.series_id)
+            .and_then(|series_id| self.internal_get_series(&series_id));
+        match series {
+            Some(series) => Some(metadata.merged_with(&series.metadata)),
+            None => Some(metadata),
+        }
+    }
 
-    //if the refund is greater than 1 yocto NEAR, we refund the predecessor that amount
-    if refund > 1 {
-        Promise::new(env::predecessor_account_id()).transfer(refund);
+    //store the metadata of a token with the current layout
+    pub(crate) fn internal_insert_token_metadata(&mut self, token_id: &TokenId, metadata: &TokenMetadata) {
+        self.token_metadata_by_id
+            .insert(token_id, &VersionedTokenMetadata::V1(metadata.clone()));
     }
-}
 
+    //refund the initial deposit based on the amount of storage that was used up
+    pub(crate) fn internal_refund_deposit(&mut self, storage_used: u64) {
+        self.internal_charge_storage(
+            &env::predecessor_account_id(),
+            storage_used,
+            env::attached_deposit(),
+        );
+    }
 
+    //refund what is left of the initial deposit once the price and the storage used are paid for
+    pub(crate) fn internal_buy_refund_deposit(&mut self, storage_used: u64, extra_spend: Balance) {
+        self.internal_charge_storage(
+            &env::predecessor_account_id(),
+            storage_used,
+            env::attached_deposit() - extra_spend,
+        );
+    }
 
-pub(crate) fn buy_refund_deposit(storage_used: u64,extra_spend:Balance) {
-    //get how much it would cost to store the information
-    let required_cost = env::storage_byte_cost() * Balance::from(storage_used);
-    //get the attached deposit
-    let attached_deposit = env::attached_deposit() - extra_spend;
+    /*
+        pay for the storage used by an account. The deposit is used first and whatever it doesn't cover
+        is taken from the account's storage balance (NEP-145). Any excess deposit is refunded.
+    */
+    pub(crate) fn internal_charge_storage(
+        &mut self,
+        account_id: &AccountId,
+        storage_used: u64,
+        deposit: Balance,
+    ) {
+        //get how much it would cost to store the information
+        let required_cost = env::storage_byte_cost() * Balance::from(storage_used);
+
+        if required_cost <= deposit {
+            //get the refund amount from the deposit - required cost
+            let refund = deposit - required_cost;
+
+            //if the refund is greater than 1 yocto NEAR, we refund the account that amount
+            if refund > 1 {
+                Promise::new(account_id.clone()).transfer(refund);
+            }
+            return;
+        }
 
-    //make sure that the attached deposit is greater than or equal to the required cost
-    assert!(
-        required_cost <= attached_deposit,
-        "Must attach {} yoctoNEAR to cover storage",
-        required_cost,
-    );
+        //the deposit didn't cover the storage so we take the rest from the storage balance
+        let shortfall = required_cost - deposit;
+        let balance = self.storage_deposits.get(account_id).unwrap_or(0);
+        let available = balance.saturating_sub(self.storage_balance_min());
+        assert!(
+            shortfall <= available,
+            "Must attach {} yoctoNEAR or deposit it with storage_deposit to cover storage",
+            shortfall,
+        );
+        self.storage_deposits.insert(account_id, &(balance - shortfall));
+    }
 
-    //get the refund amount from the attached deposit - required cost
-    let refund = attached_deposit - required_cost;
+    //make sure the token isn't locked in a running auction
+    pub(crate) fn assert_not_on_auction(&self, token_id: &TokenId) {
+        assert!(
+            self.auctions.get(token_id).is_none(),
+            "TOKEN IS ON AUCTION"
+        );
+    }
 
-    //if the refund is greater than 1 yocto NEAR, we refund the predecessor that amount
-    if refund > 1 {
-        Promise::new(env::predecessor_account_id()).transfer(refund);
+    //split a price between royalties, split payments and the owner of the token
+    pub(crate) fn internal_royalty_payout(&self, token: &Token, price: Balance) -> HashMap<AccountId, Balance> {
+        let mut payout: HashMap<AccountId, Balance> = HashMap::new();
+        let mut total_perpetual = 0;
+
+        //royalties and split payments are paid to everyone except the seller (who gets the rest at the end)
+        for (k, v) in token.royalty.iter() {
+            if k != &token.owner_id {
+                *payout.entry(k.clone()).or_insert(0) += royalty_to_payout(*v, price).0;
+                total_perpetual += *v;
+            }
+        }
+
+        for (k, v) in token.splitpayments.iter() {
+            if k != &token.owner_id {
+                *payout.entry(k.clone()).or_insert(0) += payment_splitter(*v, price).0;
+                total_perpetual += *v;
+            }
+        }
+
+        assert!(total_perpetual <= 10000, "PAYOUT OVERFLOW NOT ALLOWED");
+
+        // payout to the owner who gets 100% - total perpetual royalties
+        payout.insert(token.owner_id.clone(), royalty_to_payout(10000 - total_perpetual, price).0);
+
+        payout
+    }
+
+    //split a sale price between royalties, split payments, the treasury and the seller
+    pub(crate) fn internal_sale_payout(&self, token: &Token, price: Balance) -> HashMap<AccountId, Balance> {
+        let mut payout = self.internal_royalty_payout(token, price);
+
+        // Calculate the Commission for the Blockperks treasury, it is taken out of the seller's share.
+        // Royalties and split payments are paid first, the fee can't take more than what's left to the seller.
+        let seller_share = payout.get_mut(&token.owner_id).unwrap();
+        let for_treasury = (price * self.transaction_fee.current_fee as u128 / 10_000u128).min(*seller_share);
+        *seller_share -= for_treasury;
+        *payout.entry(self.treasury_id.clone()).or_insert(0) += for_treasury;
+
+        payout
+    }
+
+    /*
+        pay every receiver of a payout their share in NEAR. Up to the push payout limit of payees they're sent
+        their share, above it every share is credited to their proceeds and they withdraw it.
+        Returns the bytes of the new proceeds entries, they are already paid for out of the shares.
+    */
+    pub(crate) fn internal_distribute_payout(&mut self, payout: HashMap<AccountId, Balance>) -> u64 {
+        let payees = payout.values().filter(|amount| **amount > 0).count();
+        let push = payees <= self.push_payout_limit as usize;
+        let mut ledger_storage = 0;
+        for (receiver, amount) in payout {
+            if amount == 0 {
+                continue;
+            }
+            if push {
+                Promise::new(receiver).transfer(amount);
+            } else {
+                ledger_storage += self.internal_credit_proceeds(&receiver, amount);
+            }
+        }
+        ledger_storage
+    }
+
+    //hand a sold token over to the buyer. Split payments only apply to the first sale so they're cleared.
+    pub(crate) fn internal_sale_transfer(
+        &mut self,
+        token_id: &TokenId,
+        token: Token,
+        buyer_id: &AccountId,
+    ) -> Token {
+        self.assert_transferable(token_id, &token);
+        self.assert_not_rented(token_id);
+
+        //we remove the token from the seller's set and add it to the buyer's set
+        self.internal_remove_token_from_owner(&token.owner_id, token_id);
+        self.internal_add_token_to_owner(buyer_id, token_id);
+
+        //create a new token struct
+        let new_token = Token {
+            owner_id: buyer_id.clone(),
+            //reset the approval account IDs
+            approved_account_ids: Default::default(),
+            next_approval_id: token.next_approval_id,
+            //a listing made by the seller doesn't carry over to the buyer
+            price: None,
+            //we copy over the royalties from the previous token
+            royalty: token.royalty,
+
+            splitpayments: HashMap::new(),
+
+            ft_token_id: None,
+
+            series_id: token.series_id,
+            edition: token.edition,
+        };
+        //insert that new token into the tokens_by_id, replacing the old entry
+        self.internal_insert_token(token_id, &new_token);
+
+        self.internal_remove_dutch_listing(token_id);
+        self.internal_clear_user(token_id);
+
+        new_token
     }
-}
 
-impl Contract {
     //add a token to the set of tokens an owner has
     pub(crate) fn internal_add_token_to_owner(
         &mut self,
@@ -174,9 +352,44 @@ impl Contract {
         //we introduce an approval ID so that people with that approval ID can transfer the token
         approval_id: Option<u64>,
         memo: Option<String>,
+    ) -> Token {
+        let token = self.internal_move_token(sender_id, receiver_id, token_id, approval_id);
+
+        //if there was some memo attached, we log it. 
+        if let Some(memo) = memo.as_ref() {
+            env::log_str(&format!("Memo: {}", memo).to_string());
+        }
+
+        //if the approval ID was provided, the sender is the authorized ID of the logs
+        let authorized_id = approval_id.map(|_| sender_id.to_string());
+        log_nft_transfer(
+            authorized_id,
+            token.owner_id.as_str(),
+            receiver_id.as_str(),
+            &[*token_id],
+            memo,
+        );
+
+        //return the previous token object that was transferred.
+        token
+    }
+
+    //moves the NFT to the receiver_id without logging it, returns the previous token object
+    pub(crate) fn internal_move_token(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        token_id: &TokenId,
+        approval_id: Option<u64>,
     ) -> Token {
         //get the token object by passing in the token_id
-        let token = self.tokens_by_id.get(token_id).expect("No token");
+        let token = self.internal_get_token(token_id).expect("No token");
+
+        //tokens on auction stay with their owner until the auction is settled
+        self.assert_not_on_auction(token_id);
+        //bound tokens never leave their holder
+        self.assert_transferable(token_id, &token);
+        self.assert_not_rented(token_id);
 
         //if the sender doesn't equal the owner, we check if the sender is in the approval list
 		if sender_id != &token.owner_id {
@@ -220,52 +433,57 @@ impl Contract {
             //reset the approval account IDs
             approved_account_ids: Default::default(),
             next_approval_id: token.next_approval_id,
-            price:token.price.clone(),
+            //a listing made by the previous owner doesn't carry over to the receiver
+            price: None,
             //we copy over the royalties from the previous token
             royalty: token.royalty.clone(),
 
             splitpayments: token.splitpayments.clone(),
+
+            ft_token_id: None,
+
+            series_id: token.series_id,
+            edition: token.edition,
         };
         //insert that new token into the tokens_by_id, replacing the old entry 
-        self.tokens_by_id.insert(token_id, &new_token);
+        self.internal_insert_token(token_id, &new_token);
 
-        //if there was some memo attached, we log it. 
-        if let Some(memo) = memo.as_ref() {
-            env::log_str(&format!("Memo: {}", memo).to_string());
-        }
+        self.internal_remove_dutch_listing(token_id);
+        self.internal_clear_user(token_id);
 
-        // Default the authorized ID to be None for the logs.
-        let mut authorized_id = None;
-        //if the approval ID was provided, set the authorized ID equal to the sender
-        if approval_id.is_some() {
-            authorized_id = Some(sender_id.to_string());
-        }
+        token
+    }
+}
 
-        // Construct the transfer log as per the events standard.
-        let nft_transfer_log: EventLog = EventLog {
-            // Standard name ("nep171").
-            standard: NFT_STANDARD_NAME.to_string(),
-            // Version of the standard ("nft-1.0.0").
-            version: NFT_METADATA_SPEC.to_string(),
-            // The data related with the event stored in a vector.
-            event: EventLogVariant::NftTransfer(vec![NftTransferLog {
-                // The optional authorized account ID to transfer the token on behalf of the old owner.
-                authorized_id,
-                // The old owner's account ID.
-                old_owner_id: token.owner_id.to_string(),
-                // The account ID of the new owner of the token.
-                new_owner_id: receiver_id.to_string(),
-                // A vector containing the token IDs as strings.
-                token_ids: vec![token_id.to_string()],
-                // An optional memo to include.
-                memo,
-            }]),
-        };
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::*;
+
+    #[test]
+    fn sold_token_is_no_longer_listed() {
+        let mut contract = new_contract();
+        let mut token = insert_token(&mut contract, 1, "alice.near");
+        token.price = Some(ONE_NEAR);
+        token.ft_token_id = Some(account("usdc.near"));
+        contract.internal_insert_token(&1, &token);
+
+        set_context("bob.near", 0, 0);
+        let token = contract.internal_sale_transfer(&1, token, &account("bob.near"));
+        assert_eq!(token.owner_id, account("bob.near"));
+        assert!(token.price.is_none() && token.ft_token_id.is_none());
+        assert!(contract.internal_get_token(&1).unwrap().price.is_none());
+    }
 
-        // Log the serialized json.
-        env::log_str(&nft_transfer_log.to_string());
-        
-        //return the previous token object that was transferred.
-        token
+    #[test]
+    fn fee_is_capped_by_the_seller_share() {
+        let mut contract = new_contract();
+        let mut token = insert_token(&mut contract, 1, "alice.near");
+        token.royalty.insert(account("artist.near"), 5_000);
+        contract.transaction_fee.current_fee = 9_999;
+
+        let payout = contract.internal_sale_payout(&token, 10_000);
+        assert_eq!(payout[&account("artist.near")], 5_000);
+        assert_eq!(payout[&account("alice.near")], 0);
+        assert_eq!(payout[&contract.treasury_id], 5_000);
     }
-} 
\ No newline at end of file
+}
@@ -1,19 +1,38 @@
 use std::collections::HashMap;
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{LazyOption, LookupMap, UnorderedMap, UnorderedSet};
+use near_sdk::collections::{LazyOption, LookupMap, LookupSet, UnorderedMap, UnorderedSet, Vector};
 use near_sdk::json_types::{Base64VecU8, U128};
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::{
     env, near_bindgen, AccountId, Balance, CryptoHash, PanicOnDefault, Promise, PromiseOrValue,
+    PublicKey,
 };
 
 use crate::internal::*;
+use crate::merkle::*;
 pub use crate::metadata::*;
 pub use crate::mint::*;
 pub use crate::nft_core::*;
 pub use crate::approval::*;
 pub use crate::royalty::*;
 pub use crate::events::*;
+pub use crate::auction::*;
+pub use crate::dutch_auction::*;
+pub use crate::offer::*;
+pub use crate::ft_payment::*;
+pub use crate::storage::*;
+pub use crate::roles::*;
+pub use crate::pause::*;
+pub use crate::upgrade::*;
+pub use crate::series::*;
+pub use crate::voucher::*;
+pub use crate::presale::*;
+pub use crate::airdrop::*;
+pub use crate::drop::*;
+pub use crate::rental::*;
+pub use crate::validity::*;
+pub use crate::redemption::*;
+pub use crate::proceeds::*;
 
 mod internal;
 mod approval; 
@@ -23,12 +42,42 @@ mod mint;
 mod nft_core; 
 mod royalty; 
 mod events;
+mod auction;
+mod dutch_auction;
+mod offer;
+mod ft_payment;
+mod storage;
+mod roles;
+mod ownership;
+mod pause;
+mod upgrade;
+mod series;
+mod voucher;
+mod merkle;
+mod presale;
+mod airdrop;
+mod drop;
+mod soulbound;
+mod rental;
+mod validity;
+mod redemption;
+mod proceeds;
+#[cfg(test)]
+mod test_utils;
 
 
 /// This spec can be treated like a version of the standard.
 pub const NFT_METADATA_SPEC: &str = "nft-1.0.0";
 /// This is the name of the NFT standard we're using
 pub const NFT_STANDARD_NAME: &str = "nep171";
+/// Version of the NFT standard that introduced the contract_metadata_update event
+pub const NFT_CONTRACT_METADATA_UPDATE_VERSION: &str = "1.1.0";
+/// Version of the NFT standard that introduced the nft_metadata_update event
+pub const NFT_METADATA_UPDATE_VERSION: &str = "1.2.0";
+/// Standard name used for the Blockperks events that aren't covered by a NEP (auctions, ...)
+pub const BLOCKPERKS_STANDARD_NAME: &str = "blockperks";
+/// Version of the Blockperks events standard
+pub const BLOCKPERKS_EVENT_VERSION: &str = "1.0.0";
 
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
@@ -36,21 +85,124 @@ pub struct Contract {
     //contract owner
     pub owner_id: AccountId,
 
+    //account proposed as the next owner, it becomes the owner once it accepts
+    pub pending_owner_id: Option<AccountId>,
+
+    //account receiving the transaction fee of every sale
+    pub treasury_id: AccountId,
+
     //keeps track of all the token IDs for a given account
     pub tokens_per_owner: LookupMap<AccountId, UnorderedSet<TokenId>>,
 
     //keeps track of the token struct for a given token ID
-    pub tokens_by_id: UnorderedMap<TokenId, Token>,
+    pub tokens_by_id: UnorderedMap<TokenId, VersionedToken>,
 
     //keeps track of the token metadata for a given token ID
-    pub token_metadata_by_id: UnorderedMap<TokenId, TokenMetadata>,
+    pub token_metadata_by_id: UnorderedMap<TokenId, VersionedTokenMetadata>,
 
     //keeps track of the metadata for the contract
     pub metadata: LazyOption<NFTContractMetadata>,
 
     pub transaction_fee: TransactionFee,
 
-    pub tokens_minted : TokenId
+    pub tokens_minted : TokenId,
+
+    //keeps track of the running English auctions for a given token ID
+    pub auctions: UnorderedMap<TokenId, Auction>,
+
+    //keeps track of the descending price listings for a given token ID
+    pub dutch_listings: UnorderedMap<TokenId, DutchListing>,
+
+    //keeps track of the standing offers for a given offer ID
+    pub offers_by_id: UnorderedMap<OfferId, Offer>,
+
+    //keeps track of the offer IDs made on a given token or art ID
+    pub offers_per_target: LookupMap<OfferTarget, UnorderedSet<OfferId>>,
+
+    pub next_offer_id: OfferId,
+
+    //fungible tokens (NEP-141) that tokens can be priced in
+    pub accepted_ft_tokens: UnorderedSet<AccountId>,
+
+    //keeps track of the storage balance (NEP-145) deposited by a given account
+    pub storage_deposits: LookupMap<AccountId, Balance>,
+
+    //keeps track of the accounts a given role was granted to
+    pub role_members: LookupMap<Role, UnorderedSet<AccountId>>,
+
+    //which parts of the contract are currently paused
+    pub pause_state: PauseState,
+
+    //keeps track of the series for a given series ID
+    pub series_by_id: UnorderedMap<SeriesId, VersionedSeries>,
+
+    //keeps track of the copies minted from a given series
+    pub series_tokens: LookupMap<SeriesId, UnorderedSet<TokenId>>,
+
+    pub next_series_id: SeriesId,
+
+    //backend key mint vouchers have to be signed with
+    pub voucher_signer: Option<PublicKey>,
+
+    //nonces of the vouchers that were already used
+    pub used_voucher_nonces: LookupSet<u64>,
+
+    //keeps track of the sale schedule of a given lazy series
+    pub series_sales: LookupMap<SeriesId, SaleConfig>,
+
+    //keeps track of how many copies of a scheduled series a given account bought
+    pub series_minted_per_account: LookupMap<(SeriesId, AccountId), u32>,
+
+    //keeps track of the airdrop campaigns for a given campaign ID
+    pub campaigns: LookupMap<CampaignId, Campaign>,
+
+    //claimed leaves of a campaign, 64 leaves per word
+    pub claimed_bitmaps: LookupMap<(CampaignId, u64), u64>,
+
+    pub next_campaign_id: CampaignId,
+
+    //keeps track of the tokens held in escrow for a given drop key
+    pub drops: LookupMap<PublicKey, Drop>,
+
+    //tokens and series bound to their holders
+    pub non_transferable_tokens: LookupSet<TokenId>,
+    pub non_transferable_series: LookupSet<SeriesId>,
+
+    //keeps track of the account using a token and until when
+    pub token_users: LookupMap<TokenId, TokenUser>,
+
+    //keeps track of the tokens listed for rent
+    pub rental_listings: LookupMap<TokenId, RentalListing>,
+
+    //what the contract does with tokens outside of their validity window
+    pub validity_policy: ValidityPolicy,
+
+    //expired tokens marked by the validity policy
+    pub expired_tokens: LookupSet<TokenId>,
+
+    //keeps track of the merchants the copies of a series can be redeemed with
+    pub series_merchants: LookupMap<SeriesId, UnorderedSet<AccountId>>,
+
+    //keeps track of the redemption limits of a series
+    pub redemption_configs: LookupMap<SeriesId, RedemptionConfig>,
+
+    //keeps track of the redemptions of a token
+    pub redemptions: LookupMap<TokenId, Vector<Redemption>>,
+
+    //merchant approvals waiting for the holder to redeem
+    pub redemption_approvals: LookupMap<TokenId, RedemptionApproval>,
+
+    //holder approvals letting a merchant redeem, they give no other right on the token
+    pub merchant_redemption_approvals: LookupMap<TokenId, RedemptionApproval>,
+
+    //sale proceeds credited to each payee until they withdraw them
+    pub proceeds: LookupMap<AccountId, Balance>,
+
+    //sales with at most this many payees pay them directly instead of crediting their proceeds
+    pub push_payout_limit: u32,
+
+    //fungible token payouts that couldn't be sent, by receiver and fungible token, until they withdraw them
+    pub ft_proceeds: LookupMap<(AccountId, AccountId), Balance>,
 
 
     
@@ -67,6 +219,38 @@ pub enum StorageKey {
     TokensPerType,
     TokensPerTypeInner { token_type_hash: CryptoHash },
     TokenTypesLocked,
+    Auctions,
+    DutchListings,
+    OffersById,
+    OffersPerTarget,
+    OffersPerTargetInner { target_hash: CryptoHash },
+    AcceptedFtTokens,
+    StorageDeposits,
+    RoleMembers,
+    RoleMembersInner { role: Role },
+    SeriesById,
+    SeriesTokens,
+    SeriesTokensInner { series_id: SeriesId },
+    UsedVoucherNonces,
+    SeriesSales,
+    SeriesMintedPerAccount,
+    Campaigns,
+    ClaimedBitmaps,
+    Drops,
+    NonTransferableTokens,
+    NonTransferableSeries,
+    TokenUsers,
+    RentalListings,
+    ExpiredTokens,
+    SeriesMerchants,
+    SeriesMerchantsInner { series_id: SeriesId },
+    RedemptionConfigs,
+    Redemptions,
+    TokenRedemptionsInner { token_id: TokenId },
+    RedemptionApprovals,
+    Proceeds,
+    FtProceeds,
+    MerchantRedemptionApprovals,
     
 }
 
@@ -111,6 +295,9 @@ impl Contract {
             token_metadata_by_id: UnorderedMap::new(
                 StorageKey::TokenMetadataById.try_to_vec().unwrap(),
             ),
+            //the owner receives the transaction fees until another treasury is set
+            treasury_id: owner_id.clone(),
+            pending_owner_id: None,
             //set the owner_id field equal to the passed in owner_id. 
             owner_id,
             metadata: LazyOption::new(
@@ -121,10 +308,51 @@ impl Contract {
                
                 current_fee
             },
-            tokens_minted:1
+            tokens_minted:1,
+            auctions: UnorderedMap::new(StorageKey::Auctions.try_to_vec().unwrap()),
+            dutch_listings: UnorderedMap::new(StorageKey::DutchListings.try_to_vec().unwrap()),
+            offers_by_id: UnorderedMap::new(StorageKey::OffersById.try_to_vec().unwrap()),
+            offers_per_target: LookupMap::new(StorageKey::OffersPerTarget.try_to_vec().unwrap()),
+            next_offer_id: 0,
+            accepted_ft_tokens: UnorderedSet::new(StorageKey::AcceptedFtTokens.try_to_vec().unwrap()),
+            storage_deposits: LookupMap::new(StorageKey::StorageDeposits.try_to_vec().unwrap()),
+            role_members: LookupMap::new(StorageKey::RoleMembers.try_to_vec().unwrap()),
+            pause_state: PauseState::default(),
+            series_by_id: UnorderedMap::new(StorageKey::SeriesById.try_to_vec().unwrap()),
+            series_tokens: LookupMap::new(StorageKey::SeriesTokens.try_to_vec().unwrap()),
+            next_series_id: 0,
+            voucher_signer: None,
+            used_voucher_nonces: LookupSet::new(StorageKey::UsedVoucherNonces.try_to_vec().unwrap()),
+            series_sales: LookupMap::new(StorageKey::SeriesSales.try_to_vec().unwrap()),
+            series_minted_per_account: LookupMap::new(
+                StorageKey::SeriesMintedPerAccount.try_to_vec().unwrap(),
+            ),
+            campaigns: LookupMap::new(StorageKey::Campaigns.try_to_vec().unwrap()),
+            claimed_bitmaps: LookupMap::new(StorageKey::ClaimedBitmaps.try_to_vec().unwrap()),
+            next_campaign_id: 0,
+            drops: LookupMap::new(StorageKey::Drops.try_to_vec().unwrap()),
+            non_transferable_tokens: LookupSet::new(StorageKey::NonTransferableTokens.try_to_vec().unwrap()),
+            non_transferable_series: LookupSet::new(StorageKey::NonTransferableSeries.try_to_vec().unwrap()),
+            token_users: LookupMap::new(StorageKey::TokenUsers.try_to_vec().unwrap()),
+            rental_listings: LookupMap::new(StorageKey::RentalListings.try_to_vec().unwrap()),
+            validity_policy: ValidityPolicy::default(),
+            expired_tokens: LookupSet::new(StorageKey::ExpiredTokens.try_to_vec().unwrap()),
+            series_merchants: LookupMap::new(StorageKey::SeriesMerchants.try_to_vec().unwrap()),
+            redemption_configs: LookupMap::new(StorageKey::RedemptionConfigs.try_to_vec().unwrap()),
+            redemptions: LookupMap::new(StorageKey::Redemptions.try_to_vec().unwrap()),
+            redemption_approvals: LookupMap::new(StorageKey::RedemptionApprovals.try_to_vec().unwrap()),
+            proceeds: LookupMap::new(StorageKey::Proceeds.try_to_vec().unwrap()),
+            push_payout_limit: DEFAULT_PUSH_PAYOUT_LIMIT,
+            ft_proceeds: LookupMap::new(StorageKey::FtProceeds.try_to_vec().unwrap()),
+            merchant_redemption_approvals: LookupMap::new(
+                StorageKey::MerchantRedemptionApprovals.try_to_vec().unwrap(),
+            ),
                         
         };
 
+        //a fresh contract is written with the current layout and doesn't need a migration
+        env::storage_write(STATE_VERSION_KEY, &[STATE_VERSION]);
+
         //return the Contract object
         this
     }
@@ -0,0 +1,91 @@
+use crate::*;
+
+/*
+    Merkle trees are built by hashing sorted pairs: a parent is sha256(min(a, b) ++ max(a, b)).
+    Since the pairs are sorted, a proof is only the list of sibling hashes from the leaf up to the root.
+*/
+
+//hash a leaf, its content is Borsh serialized
+pub(crate) fn merkle_leaf<T: BorshSerialize>(leaf: &T) -> CryptoHash {
+    env::sha256_array(&leaf.try_to_vec().unwrap())
+}
+
+fn hash_pair(a: &CryptoHash, b: &CryptoHash) -> CryptoHash {
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+    let mut pair = [0u8; 64];
+    pair[..32].copy_from_slice(first);
+    pair[32..].copy_from_slice(second);
+    env::sha256_array(&pair)
+}
+
+//check that the leaf is part of the tree with the given root
+pub(crate) fn verify_merkle_proof(root: &CryptoHash, leaf: CryptoHash, proof: &[Base64VecU8]) -> bool {
+    let mut computed = leaf;
+    for sibling in proof {
+        let sibling: CryptoHash = match sibling.0.as_slice().try_into() {
+            Ok(sibling) => sibling,
+            Err(_) => return false,
+        };
+        computed = hash_pair(&computed, &sibling);
+    }
+    &computed == root
+}
+
+//turn a root passed in JSON into a hash
+pub(crate) fn parse_merkle_root(root: &Base64VecU8) -> CryptoHash {
+    root.0
+        .as_slice()
+        .try_into()
+        .unwrap_or_else(|_| env::panic_str("MERKLE ROOT MUST BE 32 BYTES"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree(leaves: &[CryptoHash]) -> (CryptoHash, Vec<Vec<Base64VecU8>>) {
+        //a tree of 4 leaves: proofs are [sibling, other pair]
+        let left = hash_pair(&leaves[0], &leaves[1]);
+        let right = hash_pair(&leaves[2], &leaves[3]);
+        let root = hash_pair(&left, &right);
+        let proof = |sibling: &CryptoHash, pair: &CryptoHash| {
+            vec![Base64VecU8(sibling.to_vec()), Base64VecU8(pair.to_vec())]
+        };
+        let proofs = vec![
+            proof(&leaves[1], &right),
+            proof(&leaves[0], &right),
+            proof(&leaves[3], &left),
+            proof(&leaves[2], &left),
+        ];
+        (root, proofs)
+    }
+
+    fn leaves() -> Vec<CryptoHash> {
+        ["alice.near", "bob.near", "carol.near", "dave.near"]
+            .iter()
+            .map(|account| merkle_leaf(&(account.to_string(), 2u32)))
+            .collect()
+    }
+
+    #[test]
+    fn every_leaf_is_proven() {
+        let leaves = leaves();
+        let (root, proofs) = tree(&leaves);
+        for (leaf, proof) in leaves.iter().zip(proofs.iter()) {
+            assert!(verify_merkle_proof(&root, *leaf, proof));
+        }
+    }
+
+    #[test]
+    fn wrong_leaf_or_proof_is_rejected() {
+        let leaves = leaves();
+        let (root, proofs) = tree(&leaves);
+        let outsider = merkle_leaf(&("eve.near".to_string(), 2u32));
+        assert!(!verify_merkle_proof(&root, outsider, &proofs[0]));
+        //same account with a larger quota
+        let greedy = merkle_leaf(&("alice.near".to_string(), 3u32));
+        assert!(!verify_merkle_proof(&root, greedy, &proofs[0]));
+        assert!(!verify_merkle_proof(&root, leaves[0], &proofs[1]));
+        assert!(!verify_merkle_proof(&root, leaves[0], &[Base64VecU8(vec![1, 2, 3])]));
+    }
+}
@@ -19,7 +19,7 @@ pub struct NFTContractMetadata {
     pub reference_hash: Option<Base64VecU8>, // Base64-encoded sha256 hash of JSON from reference field. Required if `reference` is included.
 }
 
-#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
 pub struct TokenMetadata {
     pub title: Option<String>, // ex. "Arch Nemesis: Mail Carrier" or "Parcel #5055"
@@ -37,7 +37,7 @@ pub struct TokenMetadata {
     pub art_id : Vec<String>
 }
 
-#[derive(BorshDeserialize, BorshSerialize)]
+#[derive(BorshDeserialize, BorshSerialize, Clone)]
 pub struct Token {
     //owner of the token
     pub owner_id: AccountId,
@@ -51,6 +51,13 @@ pub struct Token {
     pub royalty: HashMap<AccountId, u32>,
 
     pub splitpayments : HashMap<AccountId,u32>,
+
+    //fungible token the price is denominated in, NEAR if none
+    pub ft_token_id: Option<AccountId>,
+
+    //series the token is a copy of and its edition number (starting at 1)
+    pub series_id: Option<SeriesId>,
+    pub edition: Option<u64>,
 }
 
 
@@ -71,6 +78,16 @@ pub struct UpdatePriceJson {
 	pub art_id : String,    
 }
 
+//the new price of a token in a batch update, none takes it off sale
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PriceUpdate {
+    pub token_id: TokenId,
+    pub price: Option<U128>,
+    //fungible token the price is in, NEAR if none
+    pub ft_token_id: Option<AccountId>,
+}
+
 
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
@@ -125,7 +142,13 @@ pub struct JsonToken {
 
     pub price: Option<Balance>,
 
-    pub splitpayments : HashMap<AccountId,u32>
+    pub splitpayments : HashMap<AccountId,u32>,
+
+    pub ft_token_id: Option<AccountId>,
+
+    pub series_id: Option<SeriesId>,
+    //"1 of 100" for a copy of a series
+    pub edition: Option<String>,
 }
 
 pub trait NonFungibleTokenMetadata {
@@ -138,4 +161,55 @@ impl NonFungibleTokenMetadata for Contract {
     fn nft_metadata(&self) -> NFTContractMetadata {
         self.metadata.get().unwrap()
     }
-}
\ No newline at end of file
+}
+#[near_bindgen]
+impl Contract {
+    // REPLACE THE CONTRACT METADATA --- ADMIN ONLY
+    #[payable]
+    pub fn update_contract_metadata(&mut self, metadata: NFTContractMetadata, memo: Option<String>) {
+        assert_at_least_one_yocto();
+        self.assert_role(Role::Admin);
+
+        let initial_storage_usage = env::storage_usage();
+        self.metadata.set(&metadata);
+
+        let contract_metadata_update_log: EventLog = EventLog {
+            standard: NFT_STANDARD_NAME.to_string(),
+            version: NFT_CONTRACT_METADATA_UPDATE_VERSION.to_string(),
+            event: EventLogVariant::ContractMetadataUpdate(vec![ContractMetadataUpdateLog { memo }]),
+        };
+        env::log_str(&contract_metadata_update_log.to_string());
+
+        //refund any excess storage if the user attached too much. Whatever wasn't attached is taken from the storage balance.
+        let required_storage_in_bytes = env::storage_usage().saturating_sub(initial_storage_usage);
+        self.internal_refund_deposit(required_storage_in_bytes);
+    }
+
+    // REPLACE THE METADATA OF A TOKEN --- MINTER ONLY, updated_at IS SET TO NOW
+    #[payable]
+    pub fn nft_update_metadata(&mut self, token_id: TokenId, metadata: TokenMetadata, memo: Option<String>) {
+        assert_at_least_one_yocto();
+        self.assert_role(Role::Minter);
+        self.internal_get_token(&token_id).expect("TOKEN DOESNT EXIST");
+
+        let initial_storage_usage = env::storage_usage();
+        let metadata = TokenMetadata {
+            updated_at: Some(env::block_timestamp_ms()),
+            ..metadata
+        };
+        self.internal_insert_token_metadata(&token_id, &metadata);
+
+        let nft_metadata_update_log: EventLog = EventLog {
+            standard: NFT_STANDARD_NAME.to_string(),
+            version: NFT_METADATA_UPDATE_VERSION.to_string(),
+            event: EventLogVariant::NftMetadataUpdate(vec![NftMetadataUpdateLog {
+                token_ids: vec![token_id.to_string()],
+                memo,
+            }]),
+        };
+        env::log_str(&nft_metadata_update_log.to_string());
+
+        let required_storage_in_bytes = env::storage_usage().saturating_sub(initial_storage_usage);
+        self.internal_refund_deposit(required_storage_in_bytes);
+    }
+}
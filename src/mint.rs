@@ -13,6 +13,8 @@ impl Contract {
         split_payment: Option<HashMap<AccountId, u32>>, // SplitPayments
     ) -> TokenSeriesJson {
         assert_at_least_one_yocto();
+        self.assert_role(Role::Minter);
+        self.assert_not_paused(PauseFeature::Minting);
         assert!(num_to_mint > 0, "PLEASE ENTER NUM TO MINT  > 0 ");
         assert!(
             num_to_mint <= 125,
@@ -81,6 +83,12 @@ impl Contract {
             royalty: royalty_res,
 
             splitpayments: split_res,
+
+            //tokens are minted with a price in NEAR
+            ft_token_id: None,
+
+            series_id: None,
+            edition: None,
         };
 
         //insert the token ID and token struct and make sure that the token doesn't exist
@@ -89,34 +97,18 @@ impl Contract {
             // let token_id = format!("{}" , self.tokens_by_id.len()+i) ;
             let token_id = self.tokens_minted + i;
             tokens.push(token_id);
-            self.tokens_by_id.insert(&token_id, &token);
+            self.internal_insert_token(&token_id, &token);
 
             //insert the token ID and metadata
-            self.token_metadata_by_id.insert(&token_id, &metadata);
+            self.internal_insert_token_metadata(&token_id, &metadata);
 
             //call the internal method for adding the token to the owner
             self.internal_add_token_to_owner(&token.owner_id, &token_id);
-
-            // Construct the mint log as per the events standard.
-            let nft_mint_log: EventLog = EventLog {
-                // Standard name ("nep171").
-                standard: NFT_STANDARD_NAME.to_string(),
-                // Version of the standard ("nft-1.0.0").
-                version: NFT_METADATA_SPEC.to_string(),
-                // The data related with the event stored in a vector.
-                event: EventLogVariant::NftMint(vec![NftMintLog {
-                    // Owner of the token.
-                    owner_id: token.owner_id.to_string(),
-                    // Vector of token IDs that were minted.
-                    token_ids: vec![token_id.to_string()],
-                    // An optional memo to include.
-                    memo: None,
-                }]),
-            };
-            // Log the serialized json.
-            env::log_str(&nft_mint_log.to_string());
         });
 
+        // Log a single mint event for the whole batch.
+        log_nft_mint(token.owner_id.as_str(), &tokens, None);
+
         self.tokens_minted += num_to_mint;
 
         let minted = self.tokens_minted - 1;
@@ -124,8 +116,8 @@ impl Contract {
         //calculate the required storage which was the used - initial
         let required_storage_in_bytes = env::storage_usage() - initial_storage_usage;
 
-        //refund any excess storage if the user attached too much. Panic if they didn't attach enough to cover the required.
-        refund_deposit(required_storage_in_bytes);
+        //refund any excess storage if the user attached too much. Whatever wasn't attached is taken from the storage balance.
+        self.internal_refund_deposit(required_storage_in_bytes);
 
         TokenSeriesJson {
             token_id: minted,
@@ -146,6 +138,8 @@ impl Contract {
         split_payment: Option<HashMap<AccountId, u32>>, // SplitPayments
     ) -> UniqueMintJson {
         assert_at_least_one_yocto();
+        self.assert_role(Role::Minter);
+        self.assert_not_paused(PauseFeature::Minting);
         assert!(num_to_mint > 0, "PLEASE ENTER NUM TO MINT  > 0 ");
         assert!(
             num_to_mint <= 125,
@@ -196,11 +190,6 @@ impl Contract {
         //insert the token ID and token struct and make sure that the token doesn't exist
         let mut tokens: Vec<u64> = Vec::new();
         (0..num_to_mint).for_each(|i| {
-            // let token_id = format!("{}" , self.tokens_by_id.len()+i) ;
-            let token_id = self.tokens_minted + i;
-            // let type_cast = i as usize;
-            tokens.push(token_id);
-
             let type_cast = i as usize;
 
             let price: Option<u128> = if amount[type_cast].is_some() {
@@ -209,63 +198,26 @@ impl Contract {
                 None
             };
 
-            //specify the token struct that contains the owner ID
-            let token = Token {
-                //set the owner ID equal to the receiver ID passed into the function
-                owner_id: receiver_id.clone(),
-                //we set the approved account IDs to the default value (an empty map)
-                approved_account_ids: Default::default(),
-                //the next approval ID is set to 0
-                next_approval_id: 0,
-
-                // Price of NFT
+            let token_id = self.internal_unique_mint(
+                &receiver_id,
+                &metadata[type_cast],
                 price,
-                //the map of perpetual royalties for the token (The owner will get 100% - total perpetual royalties)
-                royalty: royalty_res.clone(),
-
-                splitpayments: split_res.clone(),
-            };
-
-            self.tokens_by_id.insert(&token_id, &token);
-
-            //insert the token ID and metadata
-            self.token_metadata_by_id
-                .insert(&token_id, &metadata[type_cast]);
-
-            //call the internal method for adding the token to the owner
-            self.internal_add_token_to_owner(&token.owner_id, &token_id);
-
-
-
-            // Construct the mint log as per the events standard.
-            let nft_mint_log: EventLog = EventLog {
-                // Standard name ("nep171").
-                standard: NFT_STANDARD_NAME.to_string(),
-                // Version of the standard ("nft-1.0.0").
-                version: NFT_METADATA_SPEC.to_string(),
-                // The data related with the event stored in a vector.
-                event: EventLogVariant::NftUniqueMint(vec![NftUniqueMintLog {
-                    // Owner of the token.
-                    owner_id: token.owner_id.to_string(),
-                    // Vector of token IDs that were minted.
-                    token_ids: vec![token_id.to_string()],
-                    // An optional memo to include.
-                    memo: None,
-                }]),
-            };
-            // Log the serialized json.
-            env::log_str(&nft_mint_log.to_string());
+                royalty_res.clone(),
+                split_res.clone(),
+            );
+            tokens.push(token_id);
         });
 
-        self.tokens_minted += num_to_mint;
+        // Log a single mint event for the whole batch.
+        log_nft_unique_mint(receiver_id.as_str(), &tokens, None);
 
         let minted = self.tokens_minted - 1;
 
         //calculate the required storage which was the used - initial
         let required_storage_in_bytes = env::storage_usage() - initial_storage_usage;
 
-        //refund any excess storage if the user attached too much. Panic if they didn't attach enough to cover the required.
-        refund_deposit(required_storage_in_bytes);
+        //refund any excess storage if the user attached too much. Whatever wasn't attached is taken from the storage balance.
+        self.internal_refund_deposit(required_storage_in_bytes);
 
         UniqueMintJson {
             token_id: minted,
@@ -282,122 +234,81 @@ impl Contract {
         memo: Option<String>,
         art_id: String,
     ) -> BuyNftjson {
+        self.assert_not_paused(PauseFeature::Trading);
         let initial_storage_usage = env::storage_usage();
         let receiver_id = env::predecessor_account_id();
-        let previous_token = self
-            .tokens_by_id
-            .get(&token_id)
-            .expect("TOKEN DOESNT EXIST");
-        let mut token_data = self
-            .tokens_by_id
-            .get(&token_id)
+        let token_data = self
+            .internal_get_token(&token_id)
             .expect("TOKEN DOESNT EXIST");
         assert_ne!(
             env::predecessor_account_id(),
             token_data.owner_id,
             "YOU CANNOT BUY YOUR OWN NFT"
         );
-        let price: u128 = token_data.price.expect("NOT FOR SALE");
-        let deposit = env::attached_deposit();
-        let previous_owner_id = previous_token.owner_id.clone();
-        let mut total_perpetual = 0;
-
-        assert!(deposit >= price, "DEPOSIT IS LESS THAN PRICE :{}", price,);
-
-        let mut payout_object = Payout {
-            payout: HashMap::new(),
-        };
-
-        let royalty = previous_token.royalty;
-        let splitpayments = previous_token.splitpayments;
-
-        // assert!(royalty.len() as u32 <= max_len_payout.unwrap(), "Cannot payout to that many receivers");
-        for (k, v) in royalty.iter() {
-            let key = k.clone();
-            if key != previous_owner_id {
-                payout_object
-                    .payout
-                    .insert(key, royalty_to_payout(*v, price));
-                total_perpetual += *v;
-            }
-        }
-
-        for (k, v) in splitpayments.iter() {
-            let key = k.clone();
-            if key != previous_owner_id {
-                payout_object
-                    .payout
-                    .insert(key, payment_splitter(*v, price));
-                total_perpetual += *v;
+        self.assert_transferable(&token_id, &token_data);
+        self.assert_not_expired_for_sale(&token_id);
+        //a dutch listing is charged at its current price, otherwise the token needs a fixed price
+        let price: u128 = match self.dutch_listings.get(&token_id) {
+            Some(listing) => {
+                assert!(
+                    env::block_timestamp() >= listing.starts_at,
+                    "SALE HAS NOT STARTED YET"
+                );
+                listing.price_at(env::block_timestamp())
             }
-        }
-
-        assert!(total_perpetual <= 10000, "PAYOUT OVERFLOW NOT ALLOWED");
-
-        // Calculate the Commission for Blocperks Owner Account
-        let for_treasury = price as u128 * self.calculate_current_transaction_fee() / 10_000u128;
-
-        payout_object.payout.insert(
-            token_data.owner_id,
-            royalty_to_payout(10000 - total_perpetual, price),
-        );
-
-        Promise::new(self.owner_id.clone()).transfer(for_treasury);
-
-        for (receiver, amount) in payout_object.payout {
-            if receiver == previous_owner_id {
-                Promise::new(receiver).transfer(amount.0 - for_treasury);
-            } else {
-                Promise::new(receiver).transfer(amount.0);
+            None => {
+                assert!(
+                    token_data.ft_token_id.is_none(),
+                    "TOKEN IS PRICED IN A FUNGIBLE TOKEN, BUY IT WITH ft_transfer_call"
+                );
+                token_data.price.expect("NOT FOR SALE")
             }
-        }
-        token_data.splitpayments = HashMap::new();
+        };
+        let deposit = env::attached_deposit();
 
-        //create a new token struct
-        let new_tokendata = Token {
-            owner_id: receiver_id.clone(),
-            //reset the approval account IDs
-            approved_account_ids: Default::default(),
-            next_approval_id: token_data.next_approval_id,
-            price: token_data.price.clone(),
-            //we copy over the royalties from the previous token
-            royalty: token_data.royalty.clone(),
+        assert!(deposit >= price, "DEPOSIT IS LESS THAN PRICE :{}", price,);
 
-            splitpayments: token_data.splitpayments,
-        };
-        //insert that new token into the tokens_by_id, replacing the old entry
-        self.tokens_by_id.insert(&token_id, &new_tokendata);
+        //pay out the royalties, split payments, treasury fee and the seller
+        let payout = self.internal_sale_payout(&token_data, price);
+        log_nft_sale(&token_id, &token_data.owner_id, &receiver_id, price, None, &payout);
+        let ledger_storage = self.internal_distribute_payout(payout);
 
-        //we then add the token to the receiver_id's set
-        self.internal_add_token_to_owner(&new_tokendata.owner_id, &token_id);
+        //hand the token over to the buyer (this also closes a dutch listing)
+        let old_owner_id = token_data.owner_id.clone();
+        self.internal_sale_transfer(&token_id, token_data, &receiver_id);
 
         //if there was some memo attached, we log it.
         if let Some(memo) = memo.as_ref() {
             env::log_str(&format!("Memo: {}", memo).to_string());
         }
 
-        // Construct the mint log as per the events standard.
-        let nft_mint_log: EventLog = EventLog {
+        // Construct the transfer log as per the events standard.
+        let nft_transfer_log: EventLog = EventLog {
             // Standard name ("nep171").
             standard: NFT_STANDARD_NAME.to_string(),
             // Version of the standard ("nft-1.0.0").
             version: NFT_METADATA_SPEC.to_string(),
             // The data related with the event stored in a vector.
-            event: EventLogVariant::NftMint(vec![NftMintLog {
-                owner_id: receiver_id.to_string(),
-                // Vector of token IDs that were minted.
+            event: EventLogVariant::NftTransfer(vec![NftTransferLog {
+                authorized_id: None,
+                old_owner_id: old_owner_id.to_string(),
+                new_owner_id: receiver_id.to_string(),
+                // Vector of token IDs that were sold.
                 token_ids: vec![token_id.to_string()],
-                // An optional memo to include.
-                memo: None,
+                memo,
             }]),
         };
 
         // Log the serialized json.
-        env::log_str(&nft_mint_log.to_string());
+        env::log_str(&nft_transfer_log.to_string());
 
-        let required_storage_in_bytes = env::storage_usage() - initial_storage_usage;
+        //the seller's token set may have been removed, so the storage used can go down
+        //the new proceeds entries were paid for out of the price
+        let required_storage_in_bytes = env::storage_usage()
+            .saturating_sub(initial_storage_usage)
+            .saturating_sub(ledger_storage);
 
-        buy_refund_deposit(required_storage_in_bytes, price);
+        self.internal_buy_refund_deposit(required_storage_in_bytes, price);
 
         BuyNftjson {
             token_id: token_id,
@@ -410,44 +321,21 @@ impl Contract {
     #[payable]
     pub fn nft_burn(&mut self, token_id: TokenId, art_id: String) -> BurnNFTJson {
         assert_one_yocto();
+        self.assert_not_paused(PauseFeature::Burning);
 
         let token_data = self
-            .tokens_by_id
-            .get(&token_id)
+            .internal_get_token(&token_id)
             .expect("TOKEN DOESNT EXIST");
         assert_eq!(
             token_data.owner_id,
             env::predecessor_account_id(),
             "ONLY TOKEN OWNER IS ALLOWED TO BURN"
         );
+        self.assert_not_on_auction(&token_id);
+        self.assert_not_rented(&token_id);
 
-        self.internal_remove_token_from_owner(&token_data.owner_id, &token_id);
-
-        // if let token_metadata_by_id = &mut self.token_metadata_by_id {
-        //     token_metadata_by_id.remove(&token_id);
-        // }
-        self.token_metadata_by_id.remove(&token_id);
-
-        self.tokens_by_id.remove(&token_id);
-
-        // Construct the mint log as per the events standard.
-        let nft_burn_log: EventLog = EventLog {
-            // Standard name ("nep171").
-            standard: NFT_STANDARD_NAME.to_string(),
-            // Version of the standard ("nft-1.0.0").
-            version: NFT_METADATA_SPEC.to_string(),
-            // The data related with the event stored in a vector.
-            event: EventLogVariant::NftBurn(vec![NftburnLog {
-                owner_id: (token_data.owner_id).to_string(),
-                // Vector of token IDs that were minted.
-                token_ids: vec![token_id.to_string()],
-                // An optional memo to include.
-                memo: None,
-            }]),
-        };
-
-        // Log the serialized json.
-        env::log_str(&nft_burn_log.to_string());
+        self.internal_burn(&token_id, &token_data);
+        log_nft_burn(token_data.owner_id.as_str(), &[token_id], None);
 
         BurnNFTJson {
             deleted_token: token_id,
@@ -463,17 +351,12 @@ impl Contract {
         amount: Option<U128>,
         art_id: String,
         updated_price: String,
+        ft_token_id: Option<AccountId>,                 // Fungible Token the price is in, NEAR if none
     ) -> UpdatePriceJson {
         assert_one_yocto();
-        let mut token_data = self.tokens_by_id.get(&token_id).expect("No Token Exists");
-        assert_eq!(
-            env::predecessor_account_id(),
-            token_data.owner_id,
-            "ONLY TOKEN OWNER CAN UPDATE PRICE",
-        );
-        let price: Option<u128> = Some(amount.unwrap().0);
-        token_data.price = price;
-        self.tokens_by_id.insert(&token_id, &token_data);
+        //an amount of none takes the token off sale
+        let list_log = self.internal_update_price(&token_id, amount, ft_token_id);
+        log_nft_list(vec![list_log]);
         UpdatePriceJson {
             token_id: token_id,
             updated_price,
@@ -481,29 +364,52 @@ impl Contract {
         }
     }
 
+    // LIST, RELIST OR DELIST MANY OF YOUR TOKENS AT ONCE --- A PRICE OF NONE TAKES THE TOKEN OFF SALE
     #[payable]
-    pub fn set_transaction_fee(&mut self, next_fee: u16) {
+    pub fn batch_update_price(&mut self, updates: Vec<PriceUpdate>) {
         assert_one_yocto();
-        assert_eq!(
-            env::predecessor_account_id(),
-            self.owner_id.clone(),
-            " UNAUTHORIZED - ONLY OWNER "
+        assert!(
+            updates.len() <= MAX_BATCH_SIZE,
+            "CANNOT UPDATE MORE THAN {} PRICES AT ONCE",
+            MAX_BATCH_SIZE
         );
 
+        let list_logs: Vec<NftListLog> = updates
+            .into_iter()
+            .map(|update| self.internal_update_price(&update.token_id, update.price, update.ft_token_id))
+            .collect();
+        if !list_logs.is_empty() {
+            log_nft_list(list_logs);
+        }
+    }
+
+    #[payable]
+    pub fn set_transaction_fee(&mut self, next_fee: u16) {
+        assert_one_yocto();
+        self.assert_role(Role::FeeManager);
+
         assert!(
             next_fee < 10_000,
             "YOU CANNOT SET TRANSACTION FEES TO 100% "
         );
 
+        let old_fee = self.transaction_fee.current_fee;
         self.transaction_fee.current_fee = next_fee;
+
+        let fee_update_log: EventLog = EventLog {
+            standard: BLOCKPERKS_STANDARD_NAME.to_string(),
+            version: BLOCKPERKS_EVENT_VERSION.to_string(),
+            event: EventLogVariant::FeeUpdate(vec![FeeUpdateLog {
+                old_fee,
+                new_fee: next_fee,
+                sender_id: env::predecessor_account_id().to_string(),
+            }]),
+        };
+        env::log_str(&fee_update_log.to_string());
     }
 
     pub fn get_transaction_fee(&self) -> &TransactionFee {
-        assert_eq!(
-            env::predecessor_account_id(),
-            self.owner_id.clone(),
-            "UNAUTHORIZED - ONLY OWNER"
-        );
+        self.assert_role(Role::FeeManager);
         &self.transaction_fee
     }
 
@@ -511,3 +417,119 @@ impl Contract {
         self.transaction_fee.current_fee as u128
     }
 }
+
+impl Contract {
+    //mint a single token with its own metadata to the receiver and return its token ID. The caller logs the mint.
+    pub(crate) fn internal_unique_mint(
+        &mut self,
+        receiver_id: &AccountId,
+        metadata: &TokenMetadata,
+        price: Option<Balance>,
+        royalty: HashMap<AccountId, u32>,
+        splitpayments: HashMap<AccountId, u32>,
+    ) -> TokenId {
+        let token_id = self.tokens_minted;
+        self.tokens_minted += 1;
+
+        //specify the token struct that contains the owner ID
+        let token = Token {
+            //set the owner ID equal to the receiver ID passed into the function
+            owner_id: receiver_id.clone(),
+            //we set the approved account IDs to the default value (an empty map)
+            approved_account_ids: Default::default(),
+            //the next approval ID is set to 0
+            next_approval_id: 0,
+
+            // Price of NFT
+            price,
+            //the map of perpetual royalties for the token (The owner will get 100% - total perpetual royalties)
+            royalty,
+
+            splitpayments,
+
+            //tokens are minted with a price in NEAR
+            ft_token_id: None,
+
+            series_id: None,
+            edition: None,
+        };
+
+        self.internal_insert_token(&token_id, &token);
+
+        //insert the token ID and metadata
+        self.internal_insert_token_metadata(&token_id, metadata);
+
+        //call the internal method for adding the token to the owner
+        self.internal_add_token_to_owner(&token.owner_id, &token_id);
+
+        token_id
+    }
+
+    //remove a token and everything attached to it. The caller logs the burn.
+    pub(crate) fn internal_burn(&mut self, token_id: &TokenId, token_data: &Token) {
+        self.internal_remove_token_from_owner(&token_data.owner_id, token_id);
+
+        // if let token_metadata_by_id = &mut self.token_metadata_by_id {
+        //     token_metadata_by_id.remove(&token_id);
+        // }
+        self.token_metadata_by_id.remove(token_id);
+
+        self.tokens_by_id.remove(token_id);
+
+        self.internal_remove_dutch_listing(token_id);
+        self.non_transferable_tokens.remove(token_id);
+        self.expired_tokens.remove(token_id);
+        self.internal_clear_redemptions(token_id);
+        self.internal_clear_user(token_id);
+
+        if let Some(series_id) = token_data.series_id {
+            self.internal_remove_series_token(&series_id, token_id);
+        }
+    }
+
+    //set or clear the price of a token of the caller and return its listing log
+    pub(crate) fn internal_update_price(
+        &mut self,
+        token_id: &TokenId,
+        price: Option<U128>,
+        ft_token_id: Option<AccountId>,
+    ) -> NftListLog {
+        let mut token_data = self.internal_get_token(token_id).expect("No Token Exists");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_data.owner_id,
+            "ONLY TOKEN OWNER CAN UPDATE PRICE",
+        );
+        //the price of a token on auction is decided by its bids
+        self.assert_not_on_auction(token_id);
+
+        if price.is_some() {
+            assert!(
+                self.dutch_listings.get(token_id).is_none(),
+                "TOKEN HAS A DUTCH LISTING"
+            );
+            self.assert_not_expired_for_sale(token_id);
+            //a bound token could never be bought
+            self.assert_transferable(token_id, &token_data);
+            if let Some(ft_token_id) = ft_token_id.as_ref() {
+                assert!(
+                    self.accepted_ft_tokens.contains(ft_token_id),
+                    "FUNGIBLE TOKEN IS NOT ACCEPTED AS PAYMENT"
+                );
+            }
+            token_data.ft_token_id = ft_token_id;
+        } else {
+            token_data.ft_token_id = None;
+        }
+        token_data.price = price.map(|price| price.0);
+        self.internal_insert_token(token_id, &token_data);
+
+        NftListLog {
+            owner_id: token_data.owner_id.to_string(),
+            token_id: Some(token_id.to_string()),
+            series_id: None,
+            price,
+            ft_token_id: token_data.ft_token_id.as_ref().map(|ft_token_id| ft_token_id.to_string()),
+        }
+    }
+}
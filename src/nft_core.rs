@@ -1,5 +1,6 @@
 use crate::*;
 use near_sdk::{ext_contract, Gas, PromiseResult};
+use std::collections::BTreeMap;
 
 const GAS_FOR_RESOLVE_TRANSFER: Gas = Gas(10_000_000_000_000);
 const GAS_FOR_NFT_ON_TRANSFER: Gas = Gas(25_000_000_000_000);
@@ -80,6 +81,11 @@ impl NonFungibleTokenCore for Contract {
     ) {
         //assert that the user attached exactly 1 yoctoNEAR. This is for security and so that the user will be redirected to the NEAR wallet. 
         assert_one_yocto();
+        self.assert_not_paused(PauseFeature::Transfers);
+        //an expired token burned by the validity policy has nothing left to transfer
+        if self.internal_expire_on_touch(&token_id) == Some(ExpiryAction::Burn) {
+            return;
+        }
         //get the sender to transfer the token from the sender to the receiver
         let sender_id = env::predecessor_account_id();
 
@@ -112,6 +118,10 @@ impl NonFungibleTokenCore for Contract {
     ) -> PromiseOrValue<bool> {
         //assert that the user attached exactly 1 yocto for security reasons. 
         assert_one_yocto();
+        self.assert_not_paused(PauseFeature::Transfers);
+        if self.internal_expire_on_touch(&token_id) == Some(ExpiryAction::Burn) {
+            return PromiseOrValue::Value(false);
+        }
 
         //get the sender ID 
         let sender_id = env::predecessor_account_id();
@@ -161,9 +171,10 @@ impl NonFungibleTokenCore for Contract {
     //get the information for a specific token ID
     fn nft_token(&self, token_id: TokenId) -> Option<JsonToken> {
         //if there is some token ID in the tokens_by_id collection
-        if let Some(token) = self.tokens_by_id.get(&token_id) {
+        if let Some(token) = self.internal_get_token(&token_id) {
             //we'll get the metadata for that token
-            let metadata = self.token_metadata_by_id.get(&token_id).unwrap();
+            let metadata = self.internal_get_token_metadata(&token_id).unwrap();
+            let edition = self.internal_edition_label(&token);
             //we return the JsonToken (wrapped by Some since we return an option)
             Some(JsonToken {
                 token_id,
@@ -172,7 +183,10 @@ impl NonFungibleTokenCore for Contract {
                 approved_account_ids: token.approved_account_ids,
                 royalty: token.royalty,
                 price: token.price,
-                splitpayments:token.splitpayments
+                splitpayments:token.splitpayments,
+                edition,
+                series_id: token.series_id,
+                ft_token_id: token.ft_token_id,
 
             })
         } else { //if there wasn't a token ID in the tokens_by_id collection, we return None
@@ -222,7 +236,7 @@ impl NonFungibleTokenResolver for Contract {
         }
 
         //get the token object if there is some token object
-        let mut token = if let Some(token) = self.tokens_by_id.get(&token_id) {
+        let mut token = if let Some(token) = self.internal_get_token(&token_id) {
             if token.owner_id != receiver_id {
                 //we refund the owner for releasing the storage used up by the approved account IDs
                 refund_approved_account_ids(owner_id, &approved_account_ids);
@@ -251,7 +265,7 @@ impl NonFungibleTokenResolver for Contract {
         token.approved_account_ids = approved_account_ids;
 
         //we inset the token back into the tokens_by_id collection
-        self.tokens_by_id.insert(&token_id, &token);
+        self.internal_insert_token(&token_id, &token);
 
         /*
             We need to log that the NFT was reverted back to the original owner.
@@ -285,3 +299,166 @@ impl NonFungibleTokenResolver for Contract {
         false
     }
 }
+
+//most tokens a batch method handles, to stay within the gas and log limits of a call
+pub(crate) const MAX_BATCH_SIZE: usize = 50;
+/*
+    a batch transfer can log one event per token of about 400 bytes with 64 characters account IDs.
+    32 of them and the memo stay within the 16 KiB a receipt can log.
+*/
+const MAX_BATCH_TRANSFER_SIZE: usize = 32;
+const MAX_BATCH_TRANSFER_MEMO_LENGTH: usize = 1024;
+
+#[near_bindgen]
+impl Contract {
+    /*
+        TRANSFER MANY TOKENS AT ONCE --- EACH TOKEN GOES TO ITS OWN RECEIVER, THE SENDER MUST OWN OR BE APPROVED ON ALL OF THEM.
+        EXPIRED TOKENS BURNED BY THE VALIDITY POLICY ARE SKIPPED
+    */
+    #[payable]
+    pub fn nft_batch_transfer(&mut self, token_ids: Vec<(TokenId, AccountId)>, memo: Option<String>) {
+        assert_one_yocto();
+        self.assert_not_paused(PauseFeature::Transfers);
+        assert!(
+            token_ids.len() <= MAX_BATCH_TRANSFER_SIZE,
+            "CANNOT TRANSFER MORE THAN {} TOKENS AT ONCE",
+            MAX_BATCH_TRANSFER_SIZE
+        );
+        assert!(
+            memo.as_ref().map(String::len).unwrap_or(0) <= MAX_BATCH_TRANSFER_MEMO_LENGTH,
+            "MEMO CANNOT BE LONGER THAN {} BYTES",
+            MAX_BATCH_TRANSFER_MEMO_LENGTH
+        );
+        let sender_id = env::predecessor_account_id();
+
+        //the transfers are logged once per previous owner and receiver
+        let mut transfers: BTreeMap<(AccountId, AccountId), Vec<TokenId>> = BTreeMap::new();
+        //the storage of the approvals is refunded once per previous owner
+        let mut released_storage: BTreeMap<AccountId, u64> = BTreeMap::new();
+        for (token_id, receiver_id) in token_ids {
+            if self.internal_expire_on_touch(&token_id) == Some(ExpiryAction::Burn) {
+                continue;
+            }
+            let previous_token = self.internal_move_token(&sender_id, &receiver_id, &token_id, None);
+            let released: u64 = previous_token
+                .approved_account_ids
+                .keys()
+                .map(bytes_for_approved_account_id)
+                .sum();
+            *released_storage.entry(previous_token.owner_id.clone()).or_default() += released;
+            transfers
+                .entry((previous_token.owner_id, receiver_id))
+                .or_default()
+                .push(token_id);
+        }
+
+        //the memo is logged once, with the first group
+        let mut memo = memo;
+        for ((old_owner_id, receiver_id), token_ids) in transfers {
+            //the sender is the authorized ID of the tokens it doesn't own
+            let authorized_id = Some(sender_id.to_string()).filter(|_| sender_id != old_owner_id);
+            log_nft_transfer(
+                authorized_id,
+                old_owner_id.as_str(),
+                receiver_id.as_str(),
+                &token_ids,
+                memo.take(),
+            );
+        }
+
+        for (owner_id, released) in released_storage {
+            if released > 0 {
+                Promise::new(owner_id).transfer(Balance::from(released) * env::storage_byte_cost());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::mock::VmAction;
+    use near_sdk::test_utils::{get_created_receipts, get_logs};
+
+    #[test]
+    fn batch_transfer_logs_each_group_and_refunds_each_owner_once() {
+        let mut contract = new_contract();
+        insert_token(&mut contract, 1, "alice.near");
+        insert_token(&mut contract, 2, "alice.near");
+        insert_token(&mut contract, 3, "bob.near");
+        insert_token(&mut contract, 4, "bob.near");
+        set_context("bob.near", 0, 0);
+        contract.internal_approve(&3, &account("alice.near"));
+        contract.internal_approve(&4, &account("alice.near"));
+
+        set_context("alice.near", 1, 0);
+        contract.nft_batch_transfer(
+            vec![
+                (1, account("carol.near")),
+                (3, account("carol.near")),
+                (2, account("carol.near")),
+                (4, account("carol.near")),
+            ],
+            Some("gift".to_string()),
+        );
+        for token_id in 1..=4 {
+            assert_eq!(contract.internal_get_token(&token_id).unwrap().owner_id, account("carol.near"));
+        }
+
+        //one event per previous owner and receiver, the memo only comes with the first one
+        let logs = get_logs();
+        assert_eq!(logs.len(), 2);
+        assert!(logs[0].contains(
+            r#"{"old_owner_id":"alice.near","new_owner_id":"carol.near","token_ids":["1","2"],"memo":"gift"}"#
+        ));
+        assert!(logs[1].contains(
+            r#"{"authorized_id":"alice.near","old_owner_id":"bob.near","new_owner_id":"carol.near","token_ids":["3","4"]}"#
+        ));
+
+        //bob gets the storage of both approvals back in one transfer, alice had none
+        let receipts = get_created_receipts();
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].receiver_id, account("bob.near"));
+        let released = 2 * bytes_for_approved_account_id(&account("alice.near"));
+        assert_eq!(
+            receipts[0].actions,
+            vec![VmAction::Transfer {
+                deposit: Balance::from(released) * env::storage_byte_cost()
+            }]
+        );
+    }
+
+    #[test]
+    fn batch_transfer_skips_burned_expired_tokens() {
+        let mut contract = new_contract();
+        contract.validity_policy.on_expiry = ExpiryAction::Burn;
+        insert_token(&mut contract, 1, "alice.near");
+        insert_token(&mut contract, 2, "alice.near");
+        let mut metadata = empty_metadata();
+        metadata.expires_at = Some(1);
+        contract.internal_insert_token_metadata(&1, &metadata);
+
+        set_context("alice.near", 1, 2_000_000_000);
+        contract.nft_batch_transfer(vec![(1, account("carol.near")), (2, account("carol.near"))], None);
+        assert!(contract.internal_get_token(&1).is_none());
+        assert_eq!(contract.internal_get_token(&2).unwrap().owner_id, account("carol.near"));
+    }
+
+    #[test]
+    fn largest_batch_transfer_stays_within_the_log_limit() {
+        let mut contract = new_contract();
+        let long_id = |prefix: char| format!("{}{}", prefix.to_string().repeat(59), ".near");
+        let owner_id = long_id('o');
+        for token_id in 0..MAX_BATCH_TRANSFER_SIZE as TokenId {
+            insert_token(&mut contract, u64::MAX - token_id, &owner_id);
+        }
+        let transfers = (0..MAX_BATCH_TRANSFER_SIZE as TokenId)
+            .map(|token_id| (u64::MAX - token_id, account(&format!("{}{}", token_id, long_id('r'))[..64])))
+            .collect();
+
+        set_context(&owner_id, 1, 0);
+        contract.nft_batch_transfer(transfers, Some("m".repeat(MAX_BATCH_TRANSFER_MEMO_LENGTH)));
+        assert_eq!(get_logs().len(), MAX_BATCH_TRANSFER_SIZE);
+    }
+}
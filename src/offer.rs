@@ -0,0 +1,291 @@
+use crate::*;
+
+pub type OfferId = u64;
+
+//what an offer is made on: a specific token or any token sharing an art ID
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum OfferTarget {
+    Token(TokenId),
+    ArtId(String),
+}
+
+//a standing offer. The offered NEAR is escrowed by the contract until the offer is accepted or withdrawn.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Offer {
+    pub offer_id: OfferId,
+    pub bidder_id: AccountId,
+    pub target: OfferTarget,
+    pub amount: U128,
+    //block timestamp in nanoseconds after which the offer can no longer be accepted
+    pub expires_at: u64,
+}
+
+#[near_bindgen]
+impl Contract {
+    // MAKE AN OFFER ON A TOKEN OR ON ANY TOKEN OF AN ART ID --- THE DEPOSIT MINUS STORAGE IS THE OFFERED AMOUNT
+    #[payable]
+    pub fn make_offer(
+        &mut self,
+        token_id: Option<TokenId>,
+        art_id: Option<String>,
+        expires_at: u64,
+    ) -> Offer {
+        self.assert_not_paused(PauseFeature::Trading);
+        let bidder_id = env::predecessor_account_id();
+        let target = match (token_id, art_id) {
+            (Some(token_id), None) => {
+                let token_data = self.internal_get_token(&token_id).expect("TOKEN DOESNT EXIST");
+                assert_ne!(
+                    bidder_id, token_data.owner_id,
+                    "YOU CANNOT MAKE AN OFFER ON YOUR OWN NFT"
+                );
+                //a bound token could never be handed over to the bidder
+                self.assert_transferable(&token_id, &token_data);
+                OfferTarget::Token(token_id)
+            }
+            (None, Some(art_id)) => OfferTarget::ArtId(art_id),
+            _ => env::panic_str("PASS EITHER A TOKEN ID OR AN ART ID"),
+        };
+        assert!(
+            expires_at > env::block_timestamp(),
+            "OFFER EXPIRY MUST BE IN THE FUTURE"
+        );
+
+        let initial_storage_usage = env::storage_usage();
+
+        let offer_id = self.next_offer_id;
+        self.next_offer_id += 1;
+
+        let mut offer = Offer {
+            offer_id,
+            bidder_id,
+            target,
+            amount: U128(0),
+            expires_at,
+        };
+        self.internal_add_offer(&offer);
+
+        //whatever is left of the deposit once the storage is paid for is the offered amount
+        let storage_cost = env::storage_byte_cost()
+            * Balance::from(env::storage_usage() - initial_storage_usage);
+        let deposit = env::attached_deposit();
+        assert!(
+            deposit > storage_cost,
+            "DEPOSIT MUST COVER STORAGE OF :{} AND THE OFFER",
+            storage_cost
+        );
+        offer.amount = U128(deposit - storage_cost);
+        self.offers_by_id.insert(&offer_id, &offer);
+
+        offer
+    }
+
+    // ACCEPT AN OFFER ON ONE OF YOUR TOKENS --- PAID OUT LIKE nft_buy
+    #[payable]
+    pub fn accept_offer(&mut self, offer_id: OfferId, token_id: TokenId) -> Offer {
+        assert_one_yocto();
+        self.assert_not_paused(PauseFeature::Trading);
+        let offer = self.offers_by_id.get(&offer_id).expect("NO OFFER");
+        assert!(
+            env::block_timestamp() < offer.expires_at,
+            "OFFER HAS EXPIRED"
+        );
+
+        let token_data = self.internal_get_token(&token_id).expect("TOKEN DOESNT EXIST");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token_data.owner_id,
+            "ONLY TOKEN OWNER CAN ACCEPT AN OFFER"
+        );
+        assert_ne!(
+            offer.bidder_id, token_data.owner_id,
+            "YOU CANNOT ACCEPT YOUR OWN OFFER"
+        );
+        self.assert_not_on_auction(&token_id);
+        self.assert_not_expired_for_sale(&token_id);
+
+        //the token has to be the one the offer was made on
+        let matches_target = match &offer.target {
+            OfferTarget::Token(offer_token_id) => *offer_token_id == token_id,
+            OfferTarget::ArtId(art_id) => self
+                .internal_get_token_metadata(&token_id)
+                .map(|metadata| metadata.art_id.contains(art_id))
+                .unwrap_or(false),
+        };
+        assert!(matches_target, "OFFER IS NOT FOR THIS TOKEN");
+
+        //the bidder gets back what their offer paid for storage
+        let storage_refund = self.internal_remove_offer(&offer);
+        if storage_refund > 0 {
+            Promise::new(offer.bidder_id.clone()).transfer(storage_refund);
+        }
+
+        //pay out the royalties, split payments, treasury fee and the seller
+        let payout = self.internal_sale_payout(&token_data, offer.amount.0);
+        log_nft_sale(
+            &token_id,
+            &token_data.owner_id,
+            &offer.bidder_id,
+            offer.amount.0,
+            None,
+            &payout,
+        );
+        self.internal_distribute_payout(payout);
+
+        //hand the token over to the bidder
+        let old_owner_id = token_data.owner_id.clone();
+        self.internal_sale_transfer(&token_id, token_data, &offer.bidder_id);
+
+        let nft_transfer_log: EventLog = EventLog {
+            standard: NFT_STANDARD_NAME.to_string(),
+            version: NFT_METADATA_SPEC.to_string(),
+            event: EventLogVariant::NftTransfer(vec![NftTransferLog {
+                authorized_id: None,
+                old_owner_id: old_owner_id.to_string(),
+                new_owner_id: offer.bidder_id.to_string(),
+                token_ids: vec![token_id.to_string()],
+                memo: None,
+            }]),
+        };
+        env::log_str(&nft_transfer_log.to_string());
+
+        offer
+    }
+
+    // WITHDRAW AN OFFER --- REFUNDS THE OFFERED AMOUNT AND ITS STORAGE
+    #[payable]
+    pub fn withdraw_offer(&mut self, offer_id: OfferId) -> Offer {
+        assert_one_yocto();
+        let offer = self.offers_by_id.get(&offer_id).expect("NO OFFER");
+        assert_eq!(
+            env::predecessor_account_id(),
+            offer.bidder_id,
+            "ONLY THE BIDDER CAN WITHDRAW AN OFFER"
+        );
+
+        let storage_refund = self.internal_remove_offer(&offer);
+        Promise::new(offer.bidder_id.clone()).transfer(offer.amount.0 + storage_refund);
+
+        offer
+    }
+
+    pub fn get_offer(&self, offer_id: OfferId) -> Option<Offer> {
+        self.offers_by_id.get(&offer_id)
+    }
+
+    //Query for the offers made on a specific token using pagination
+    pub fn get_offers_for_token(
+        &self,
+        token_id: TokenId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<Offer> {
+        self.internal_offers_for_target(&OfferTarget::Token(token_id), from_index, limit)
+    }
+
+    //Query for the offers made on any token of an art ID using pagination
+    pub fn get_offers_for_art_id(
+        &self,
+        art_id: String,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<Offer> {
+        self.internal_offers_for_target(&OfferTarget::ArtId(art_id), from_index, limit)
+    }
+}
+
+impl Contract {
+    //store an offer and add it to the set of offers for its target
+    pub(crate) fn internal_add_offer(&mut self, offer: &Offer) {
+        self.offers_by_id.insert(&offer.offer_id, offer);
+
+        let mut offers_set = self.offers_per_target.get(&offer.target).unwrap_or_else(|| {
+            //if the target doesn't have any offers, we create a new unordered set
+            UnorderedSet::new(
+                StorageKey::OffersPerTargetInner {
+                    //we get a new unique prefix for the collection
+                    target_hash: env::sha256_array(&offer.target.try_to_vec().unwrap()),
+                }
+                .try_to_vec()
+                .unwrap(),
+            )
+        });
+        offers_set.insert(&offer.offer_id);
+        self.offers_per_target.insert(&offer.target, &offers_set);
+    }
+
+    //remove an offer and return how much NEAR the released storage is worth
+    pub(crate) fn internal_remove_offer(&mut self, offer: &Offer) -> Balance {
+        let initial_storage_usage = env::storage_usage();
+
+        self.offers_by_id.remove(&offer.offer_id);
+
+        if let Some(mut offers_set) = self.offers_per_target.get(&offer.target) {
+            offers_set.remove(&offer.offer_id);
+            //if the set is now empty, we remove the target from the offers_per_target collection
+            if offers_set.is_empty() {
+                self.offers_per_target.remove(&offer.target);
+            } else {
+                self.offers_per_target.insert(&offer.target, &offers_set);
+            }
+        }
+
+        env::storage_byte_cost()
+            * Balance::from(initial_storage_usage.saturating_sub(env::storage_usage()))
+    }
+
+    pub(crate) fn internal_offers_for_target(
+        &self,
+        target: &OfferTarget,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<Offer> {
+        let offers_set = if let Some(offers_set) = self.offers_per_target.get(target) {
+            offers_set
+        } else {
+            return vec![];
+        };
+
+        //where to start pagination - if we have a from_index, we'll use that - otherwise start from 0 index
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        offers_set.iter()
+            //skip to the index we specified in the start variable
+            .skip(start as usize)
+            //take the first "limit" elements in the vector. If we didn't specify a limit, use 50
+            .take(limit.unwrap_or(50) as usize)
+            .map(|offer_id| self.offers_by_id.get(&offer_id).unwrap())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    #[should_panic(expected = "Trading IS PAUSED")]
+    fn no_offers_while_trading_is_paused() {
+        let mut contract = new_contract();
+        insert_token(&mut contract, 1, "alice.near");
+        set_context("owner.near", 1, 0);
+        contract.pause(PauseFeature::Trading);
+
+        set_context("bob.near", ONE_NEAR, 0);
+        contract.make_offer(Some(1), None, 100);
+    }
+
+    #[test]
+    #[should_panic(expected = "TOKEN IS NOT TRANSFERABLE")]
+    fn bound_token_cannot_get_an_offer() {
+        let mut contract = new_contract();
+        insert_token(&mut contract, 1, "alice.near");
+        contract.non_transferable_tokens.insert(&1);
+        set_context("bob.near", ONE_NEAR, 0);
+        contract.make_offer(Some(1), None, 100);
+    }
+}
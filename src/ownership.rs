@@ -0,0 +1,113 @@
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    // PROPOSE A NEW CONTRACT OWNER --- THE OWNERSHIP ONLY MOVES ONCE THEY ACCEPT IT
+    #[payable]
+    pub fn propose_owner(&mut self, new_owner_id: AccountId) {
+        assert_one_yocto();
+        self.assert_owner();
+        assert_ne!(
+            new_owner_id, self.owner_id,
+            "ACCOUNT IS ALREADY THE OWNER"
+        );
+
+        self.pending_owner_id = Some(new_owner_id.clone());
+
+        let ownership_log: EventLog = EventLog {
+            standard: BLOCKPERKS_STANDARD_NAME.to_string(),
+            version: BLOCKPERKS_EVENT_VERSION.to_string(),
+            event: EventLogVariant::OwnershipPropose(vec![OwnershipProposalLog {
+                owner_id: self.owner_id.to_string(),
+                proposed_owner_id: new_owner_id.to_string(),
+            }]),
+        };
+        env::log_str(&ownership_log.to_string());
+    }
+
+    #[payable]
+    pub fn accept_ownership(&mut self) {
+        assert_one_yocto();
+        let new_owner_id = env::predecessor_account_id();
+        assert_eq!(
+            Some(&new_owner_id),
+            self.pending_owner_id.as_ref(),
+            "UNAUTHORIZED - ONLY PROPOSED OWNER"
+        );
+
+        let old_owner_id = std::mem::replace(&mut self.owner_id, new_owner_id.clone());
+        self.pending_owner_id = None;
+
+        let ownership_log: EventLog = EventLog {
+            standard: BLOCKPERKS_STANDARD_NAME.to_string(),
+            version: BLOCKPERKS_EVENT_VERSION.to_string(),
+            event: EventLogVariant::OwnershipAccept(vec![OwnershipTransferLog {
+                old_owner_id: old_owner_id.to_string(),
+                new_owner_id: new_owner_id.to_string(),
+            }]),
+        };
+        env::log_str(&ownership_log.to_string());
+    }
+
+    #[payable]
+    pub fn cancel_ownership_proposal(&mut self) {
+        assert_one_yocto();
+        self.assert_owner();
+        let proposed_owner_id = self
+            .pending_owner_id
+            .take()
+            .expect("NO OWNERSHIP PROPOSAL");
+
+        let ownership_log: EventLog = EventLog {
+            standard: BLOCKPERKS_STANDARD_NAME.to_string(),
+            version: BLOCKPERKS_EVENT_VERSION.to_string(),
+            event: EventLogVariant::OwnershipCancel(vec![OwnershipProposalLog {
+                owner_id: self.owner_id.to_string(),
+                proposed_owner_id: proposed_owner_id.to_string(),
+            }]),
+        };
+        env::log_str(&ownership_log.to_string());
+    }
+
+    // SET THE ACCOUNT RECEIVING THE TRANSACTION FEE OF EVERY SALE
+    #[payable]
+    pub fn set_treasury(&mut self, treasury_id: AccountId) {
+        assert_one_yocto();
+        self.assert_owner();
+
+        let old_treasury_id = std::mem::replace(&mut self.treasury_id, treasury_id.clone());
+
+        let treasury_log: EventLog = EventLog {
+            standard: BLOCKPERKS_STANDARD_NAME.to_string(),
+            version: BLOCKPERKS_EVENT_VERSION.to_string(),
+            event: EventLogVariant::TreasuryUpdate(vec![TreasuryUpdateLog {
+                old_treasury_id: old_treasury_id.to_string(),
+                new_treasury_id: treasury_id.to_string(),
+            }]),
+        };
+        env::log_str(&treasury_log.to_string());
+    }
+
+    pub fn get_owner(&self) -> AccountId {
+        self.owner_id.clone()
+    }
+
+    pub fn get_pending_owner(&self) -> Option<AccountId> {
+        self.pending_owner_id.clone()
+    }
+
+    pub fn get_treasury(&self) -> AccountId {
+        self.treasury_id.clone()
+    }
+}
+
+impl Contract {
+    //make sure the caller is the contract owner
+    pub(crate) fn assert_owner(&self) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            " UNAUTHORIZED - ONLY OWNER "
+        );
+    }
+}
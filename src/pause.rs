@@ -0,0 +1,103 @@
+use crate::*;
+
+//the parts of the contract that can be paused independently
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum PauseFeature {
+    //batch_mint and unique_mint
+    Minting,
+    //nft_buy, nft_transfer_payout and the auction, offer and fungible token sales
+    Trading,
+    //nft_transfer and nft_transfer_call
+    Transfers,
+    //nft_burn and burning expired tokens
+    Burning,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PauseState {
+    pub minting: bool,
+    pub trading: bool,
+    pub transfers: bool,
+    pub burning: bool,
+}
+
+impl PauseState {
+    pub fn is_paused(&self, feature: PauseFeature) -> bool {
+        match feature {
+            PauseFeature::Minting => self.minting,
+            PauseFeature::Trading => self.trading,
+            PauseFeature::Transfers => self.transfers,
+            PauseFeature::Burning => self.burning,
+        }
+    }
+
+    fn set_paused(&mut self, feature: PauseFeature, paused: bool) {
+        match feature {
+            PauseFeature::Minting => self.minting = paused,
+            PauseFeature::Trading => self.trading = paused,
+            PauseFeature::Transfers => self.transfers = paused,
+            PauseFeature::Burning => self.burning = paused,
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    #[payable]
+    pub fn pause(&mut self, feature: PauseFeature) {
+        assert_one_yocto();
+        self.assert_role(Role::Pauser);
+        self.internal_set_paused(feature, true);
+    }
+
+    #[payable]
+    pub fn unpause(&mut self, feature: PauseFeature) {
+        assert_one_yocto();
+        self.assert_role(Role::Pauser);
+        self.internal_set_paused(feature, false);
+    }
+
+    pub fn get_pause_state(&self) -> PauseState {
+        self.pause_state.clone()
+    }
+
+    pub fn is_paused(&self, feature: PauseFeature) -> bool {
+        self.pause_state.is_paused(feature)
+    }
+}
+
+impl Contract {
+    //make sure the feature isn't paused
+    pub(crate) fn assert_not_paused(&self, feature: PauseFeature) {
+        assert!(
+            !self.pause_state.is_paused(feature),
+            "{:?} IS PAUSED",
+            feature
+        );
+    }
+
+    pub(crate) fn internal_set_paused(&mut self, feature: PauseFeature, paused: bool) {
+        //nothing to log if the feature is already in that state
+        if self.pause_state.is_paused(feature) == paused {
+            return;
+        }
+        self.pause_state.set_paused(feature, paused);
+
+        let pause_log = PauseLog {
+            feature,
+            sender_id: env::predecessor_account_id().to_string(),
+        };
+        let pause_log: EventLog = EventLog {
+            standard: BLOCKPERKS_STANDARD_NAME.to_string(),
+            version: BLOCKPERKS_EVENT_VERSION.to_string(),
+            event: if paused {
+                EventLogVariant::ContractPause(vec![pause_log])
+            } else {
+                EventLogVariant::ContractUnpause(vec![pause_log])
+            },
+        };
+        env::log_str(&pause_log.to_string());
+    }
+}
@@ -0,0 +1,130 @@
+use crate::*;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum SalePhase {
+    Closed,
+    //only the accounts of the allowlist can buy, up to their quota
+    Allowlist,
+    Public,
+}
+
+/*
+    sale schedule of a lazy series. The allowlist is the Merkle root of the Borsh serialized
+    (account ID, quota) leaves, a quota being how many copies the account can buy in total.
+*/
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SaleConfig {
+    //block timestamps in nanoseconds at which each phase starts, the sale is closed before
+    pub allowlist_starts_at: u64,
+    pub public_starts_at: u64,
+    pub merkle_root: Base64VecU8,
+    //how many copies an account can buy in total once the sale is public, unlimited if none
+    pub public_limit: Option<u32>,
+}
+
+impl SaleConfig {
+    pub fn phase_at(&self, timestamp: u64) -> SalePhase {
+        if timestamp >= self.public_starts_at {
+            SalePhase::Public
+        } else if timestamp >= self.allowlist_starts_at {
+            SalePhase::Allowlist
+        } else {
+            SalePhase::Closed
+        }
+    }
+}
+
+//proof that the buyer is on the allowlist with the given quota
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AllowlistProof {
+    pub quota: u32,
+    pub proof: Vec<Base64VecU8>,
+}
+
+#[near_bindgen]
+impl Contract {
+    // SCHEDULE THE SALE OF A LAZY SERIES --- ONLY ITS CREATOR CAN
+    #[payable]
+    pub fn set_series_sale(&mut self, series_id: SeriesId, sale: SaleConfig) {
+        assert_at_least_one_yocto();
+        let series = self.internal_get_series(&series_id).expect("SERIES DOESNT EXIST");
+        assert_eq!(
+            env::predecessor_account_id(),
+            series.creator_id,
+            "ONLY SERIES CREATOR CAN SET ITS SALE"
+        );
+        assert!(
+            sale.allowlist_starts_at <= sale.public_starts_at,
+            "ALLOWLIST PHASE MUST START BEFORE THE PUBLIC PHASE"
+        );
+        parse_merkle_root(&sale.merkle_root);
+
+        let initial_storage_usage = env::storage_usage();
+        self.series_sales.insert(&series_id, &sale);
+
+        //refund any excess storage if the user attached too much. Whatever wasn't attached is taken from the storage balance.
+        let required_storage_in_bytes = env::storage_usage().saturating_sub(initial_storage_usage);
+        self.internal_refund_deposit(required_storage_in_bytes);
+    }
+
+    pub fn get_series_sale(&self, series_id: SeriesId) -> Option<SaleConfig> {
+        self.series_sales.get(&series_id)
+    }
+
+    //a series without a sale schedule is always public
+    pub fn get_sale_phase(&self, series_id: SeriesId) -> SalePhase {
+        self.series_sales
+            .get(&series_id)
+            .map(|sale| sale.phase_at(env::block_timestamp()))
+            .unwrap_or(SalePhase::Public)
+    }
+
+    //get how many copies of a scheduled series an account bought
+    pub fn get_series_minted_by(&self, series_id: SeriesId, account_id: AccountId) -> u32 {
+        self.series_minted_per_account
+            .get(&(series_id, account_id))
+            .unwrap_or(0)
+    }
+}
+
+impl Contract {
+    //make sure the buyer can buy a copy in the current phase and count it
+    pub(crate) fn internal_record_series_purchase(
+        &mut self,
+        series_id: SeriesId,
+        buyer_id: &AccountId,
+        allowlist_proof: Option<AllowlistProof>,
+    ) {
+        let sale = if let Some(sale) = self.series_sales.get(&series_id) {
+            sale
+        } else {
+            return;
+        };
+
+        let key = (series_id, buyer_id.clone());
+        let minted = self.series_minted_per_account.get(&key).unwrap_or(0);
+        match sale.phase_at(env::block_timestamp()) {
+            SalePhase::Closed => env::panic_str("SALE HAS NOT STARTED YET"),
+            SalePhase::Allowlist => {
+                let allowlist_proof = allowlist_proof.expect("ALLOWLIST PROOF REQUIRED");
+                let root = parse_merkle_root(&sale.merkle_root);
+                let leaf = merkle_leaf(&(buyer_id, allowlist_proof.quota));
+                assert!(
+                    verify_merkle_proof(&root, leaf, &allowlist_proof.proof),
+                    "NOT ON THE ALLOWLIST"
+                );
+                assert!(minted < allowlist_proof.quota, "ALLOWLIST QUOTA REACHED");
+            }
+            SalePhase::Public => {
+                if let Some(public_limit) = sale.public_limit {
+                    assert!(minted < public_limit, "MINT LIMIT REACHED");
+                }
+            }
+        }
+
+        self.series_minted_per_account.insert(&key, &(minted + 1));
+    }
+}
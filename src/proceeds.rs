@@ -0,0 +1,143 @@
+use crate::*;
+use near_sdk::{Gas, PromiseResult};
+
+const GAS_FOR_RESOLVE_WITHDRAW: Gas = Gas(5_000_000_000_000);
+//royalty and split payment accounts (at most 10), the seller and the treasury: every sale pays directly
+pub const DEFAULT_PUSH_PAYOUT_LIMIT: u32 = 12;
+
+#[near_bindgen]
+impl Contract {
+    // WITHDRAW YOUR SALE PROCEEDS --- ALL OF THEM IF NO AMOUNT IS GIVEN
+    #[payable]
+    pub fn withdraw(&mut self, amount: Option<U128>) -> Promise {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let balance = self.proceeds.get(&account_id).unwrap_or(0);
+        let amount = amount.map(|amount| amount.0).unwrap_or(balance);
+        assert!(amount > 0, "NOTHING TO WITHDRAW");
+        assert!(amount <= balance, "BALANCE IS LESS THAN AMOUNT :{}", amount);
+
+        let sent = if amount == balance {
+            //the entry was paid for out of the proceeds, its storage goes back with them
+            let initial_storage_usage = env::storage_usage();
+            self.proceeds.remove(&account_id);
+            let freed_storage = initial_storage_usage.saturating_sub(env::storage_usage());
+            amount + env::storage_byte_cost() * Balance::from(freed_storage)
+        } else {
+            self.proceeds.insert(&account_id, &(balance - amount));
+            amount
+        };
+
+        Promise::new(account_id.clone()).transfer(sent).then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_RESOLVE_WITHDRAW)
+                .resolve_withdraw(account_id, U128(sent)),
+        )
+    }
+
+    //a withdrawal that couldn't be sent, e.g. to a deleted account, is credited back
+    #[private]
+    pub fn resolve_withdraw(&mut self, account_id: AccountId, amount: U128) -> bool {
+        if matches!(env::promise_result(0), PromiseResult::Successful(_)) {
+            return true;
+        }
+        self.internal_credit_proceeds(&account_id, amount.0);
+        false
+    }
+
+    pub fn balance_of_proceeds(&self, account_id: AccountId) -> U128 {
+        U128(self.proceeds.get(&account_id).unwrap_or(0))
+    }
+
+    // SET UP TO HOW MANY PAYEES A SALE PAYS THEM DIRECTLY --- ABOVE IT THEY ARE CREDITED AND WITHDRAW
+    #[payable]
+    pub fn set_push_payout_limit(&mut self, limit: u32) {
+        assert_one_yocto();
+        self.assert_role(Role::Admin);
+        self.push_payout_limit = limit;
+    }
+
+    pub fn get_push_payout_limit(&self) -> u32 {
+        self.push_payout_limit
+    }
+}
+
+impl Contract {
+    /*
+        credit an amount to the proceeds of an account and return the bytes of the entry it created, if any.
+        A new entry is paid for out of the amount, an amount that can't pay for it is sent right away.
+    */
+    pub(crate) fn internal_credit_proceeds(&mut self, account_id: &AccountId, amount: Balance) -> u64 {
+        if let Some(balance) = self.proceeds.get(account_id) {
+            self.proceeds.insert(account_id, &(balance + amount));
+            return 0;
+        }
+
+        let initial_storage_usage = env::storage_usage();
+        self.proceeds.insert(account_id, &amount);
+        let entry_storage = env::storage_usage() - initial_storage_usage;
+        let entry_cost = env::storage_byte_cost() * Balance::from(entry_storage);
+        if amount > entry_cost {
+            self.proceeds.insert(account_id, &(amount - entry_cost));
+            entry_storage
+        } else {
+            self.proceeds.remove(account_id);
+            Promise::new(account_id.clone()).transfer(amount);
+            0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    fn payout(payees: &[(&str, Balance)]) -> HashMap<AccountId, Balance> {
+        payees
+            .iter()
+            .map(|(account_id, amount)| (account(account_id), *amount))
+            .collect()
+    }
+
+    #[test]
+    fn payouts_above_the_limit_are_credited() {
+        let mut contract = new_contract();
+        assert_eq!(contract.get_push_payout_limit(), DEFAULT_PUSH_PAYOUT_LIMIT);
+        contract.push_payout_limit = 2;
+
+        //a sale with few payees is still pushed
+        contract.internal_distribute_payout(payout(&[("seller.near", 90), ("treasury.near", 10)]));
+        assert_eq!(contract.balance_of_proceeds(account("seller.near")), U128(0));
+
+        let ledger_storage = contract.internal_distribute_payout(payout(&[
+            ("seller.near", 8 * ONE_NEAR),
+            ("artist.near", ONE_NEAR),
+            ("treasury.near", ONE_NEAR),
+        ]));
+        //every new entry is paid for out of its share
+        let seller = contract.balance_of_proceeds(account("seller.near")).0;
+        let artist = contract.balance_of_proceeds(account("artist.near")).0;
+        let treasury = contract.balance_of_proceeds(account("treasury.near")).0;
+        assert!(ledger_storage > 0 && seller < 8 * ONE_NEAR);
+        assert_eq!(
+            seller + artist + treasury,
+            10 * ONE_NEAR - env::storage_byte_cost() * Balance::from(ledger_storage)
+        );
+
+        //a share too small to pay for its entry is sent right away
+        contract.internal_distribute_payout(payout(&[
+            ("seller.near", 5),
+            ("artist.near", 0),
+            ("collector.near", 1),
+            ("treasury.near", 1),
+        ]));
+        assert_eq!(contract.balance_of_proceeds(account("seller.near")), U128(seller + 5));
+        assert_eq!(contract.balance_of_proceeds(account("collector.near")), U128(0));
+
+        //withdrawing everything gives the entry's storage back
+        set_context("artist.near", 1, 0);
+        contract.withdraw(None);
+        assert!(contract.proceeds.get(&account("artist.near")).is_none());
+    }
+}
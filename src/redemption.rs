@@ -0,0 +1,428 @@
+use crate::*;
+
+//limits on how the copies of a series can be redeemed, they apply to each token
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RedemptionConfig {
+    //how many times a token can be redeemed, unlimited if none
+    pub max_redemptions: Option<u32>,
+    //nanoseconds between two redemptions of a token
+    pub cooldown: u64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Redemption {
+    pub merchant_id: AccountId,
+    pub owner_id: AccountId,
+    pub memo: Option<String>,
+    //block timestamp in nanoseconds
+    pub redeemed_at: u64,
+}
+
+//a merchant's go-ahead for the holder of a token to redeem it with them, or the holder's go-ahead for a merchant to redeem it
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RedemptionApproval {
+    pub merchant_id: AccountId,
+    //the approval is void once the token changes hands
+    pub owner_id: AccountId,
+}
+
+#[near_bindgen]
+impl Contract {
+    // REGISTER A MERCHANT THE COPIES OF A SERIES CAN BE REDEEMED WITH --- ONLY ITS CREATOR CAN
+    #[payable]
+    pub fn add_series_merchant(&mut self, series_id: SeriesId, merchant_id: AccountId) {
+        assert_at_least_one_yocto();
+        self.assert_series_creator(&series_id);
+
+        let initial_storage_usage = env::storage_usage();
+        let mut merchants = self.series_merchants.get(&series_id).unwrap_or_else(|| {
+            UnorderedSet::new(
+                StorageKey::SeriesMerchantsInner { series_id }
+                    .try_to_vec()
+                    .unwrap(),
+            )
+        });
+        merchants.insert(&merchant_id);
+        self.series_merchants.insert(&series_id, &merchants);
+
+        //refund any excess storage if the user attached too much. Whatever wasn't attached is taken from the storage balance.
+        let required_storage_in_bytes = env::storage_usage().saturating_sub(initial_storage_usage);
+        self.internal_refund_deposit(required_storage_in_bytes);
+    }
+
+    #[payable]
+    pub fn remove_series_merchant(&mut self, series_id: SeriesId, merchant_id: AccountId) {
+        assert_one_yocto();
+        self.assert_series_creator(&series_id);
+        if let Some(mut merchants) = self.series_merchants.get(&series_id) {
+            merchants.remove(&merchant_id);
+            self.series_merchants.insert(&series_id, &merchants);
+        }
+    }
+
+    pub fn get_series_merchants(&self, series_id: SeriesId) -> Vec<AccountId> {
+        self.series_merchants
+            .get(&series_id)
+            .map(|merchants| merchants.to_vec())
+            .unwrap_or_default()
+    }
+
+    // SET HOW OFTEN THE COPIES OF A SERIES CAN BE REDEEMED --- ONLY ITS CREATOR CAN
+    #[payable]
+    pub fn set_series_redemption(&mut self, series_id: SeriesId, config: RedemptionConfig) {
+        assert_at_least_one_yocto();
+        self.assert_series_creator(&series_id);
+
+        let initial_storage_usage = env::storage_usage();
+        self.redemption_configs.insert(&series_id, &config);
+
+        let required_storage_in_bytes = env::storage_usage().saturating_sub(initial_storage_usage);
+        self.internal_refund_deposit(required_storage_in_bytes);
+    }
+
+    pub fn get_series_redemption(&self, series_id: SeriesId) -> Option<RedemptionConfig> {
+        self.redemption_configs.get(&series_id)
+    }
+
+    // LET THE HOLDER OF A TOKEN REDEEM IT WITH YOU --- CALLED BY A MERCHANT OF ITS SERIES
+    #[payable]
+    pub fn approve_redemption(&mut self, token_id: TokenId) {
+        assert_at_least_one_yocto();
+        let merchant_id = env::predecessor_account_id();
+        let token = self.internal_get_token(&token_id).expect("TOKEN DOESNT EXIST");
+        self.assert_series_merchant(&token, &merchant_id);
+
+        let initial_storage_usage = env::storage_usage();
+        self.redemption_approvals.insert(
+            &token_id,
+            &RedemptionApproval {
+                merchant_id,
+                owner_id: token.owner_id,
+            },
+        );
+
+        let required_storage_in_bytes = env::storage_usage().saturating_sub(initial_storage_usage);
+        self.internal_refund_deposit(required_storage_in_bytes);
+    }
+
+    pub fn get_redemption_approval(&self, token_id: TokenId) -> Option<RedemptionApproval> {
+        self.redemption_approvals.get(&token_id)
+    }
+
+    // LET A MERCHANT OF ITS SERIES REDEEM YOUR TOKEN --- IT CAN'T DO ANYTHING ELSE WITH IT
+    #[payable]
+    pub fn approve_merchant_redemption(&mut self, token_id: TokenId, merchant_id: AccountId) {
+        assert_at_least_one_yocto();
+        let token = self.internal_get_token(&token_id).expect("TOKEN DOESNT EXIST");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token.owner_id,
+            "ONLY TOKEN OWNER CAN APPROVE A MERCHANT"
+        );
+        self.assert_series_merchant(&token, &merchant_id);
+
+        let initial_storage_usage = env::storage_usage();
+        self.merchant_redemption_approvals.insert(
+            &token_id,
+            &RedemptionApproval {
+                merchant_id,
+                owner_id: token.owner_id,
+            },
+        );
+
+        let required_storage_in_bytes = env::storage_usage().saturating_sub(initial_storage_usage);
+        self.internal_refund_deposit(required_storage_in_bytes);
+    }
+
+    #[payable]
+    pub fn revoke_merchant_redemption(&mut self, token_id: TokenId) {
+        assert_one_yocto();
+        let token = self.internal_get_token(&token_id).expect("TOKEN DOESNT EXIST");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token.owner_id,
+            "ONLY TOKEN OWNER CAN REVOKE A MERCHANT"
+        );
+        self.merchant_redemption_approvals.remove(&token_id);
+    }
+
+    pub fn get_merchant_redemption_approval(&self, token_id: TokenId) -> Option<RedemptionApproval> {
+        self.merchant_redemption_approvals.get(&token_id)
+    }
+
+    /*
+        REDEEM A PERK. EITHER THE HOLDER REDEEMS IT WITH THE MERCHANT WHO APPROVED THE REDEMPTION,
+        OR A MERCHANT THE HOLDER APPROVED WITH approve_merchant_redemption REDEEMS IT. ATTACH THE STORAGE OF THE RECORD.
+        RETURNS NONE WHEN THE PERK HAD EXPIRED AND THE VALIDITY POLICY MARKED OR BURNED IT INSTEAD.
+    */
+    #[payable]
+    pub fn redeem(&mut self, token_id: TokenId, memo: Option<String>) -> Option<Redemption> {
+        assert_at_least_one_yocto();
+        let token = self.internal_get_token(&token_id).expect("TOKEN DOESNT EXIST");
+        let sender_id = env::predecessor_account_id();
+
+        let merchant_id = if sender_id == token.owner_id {
+            let approval = self
+                .redemption_approvals
+                .get(&token_id)
+                .expect("REDEMPTION NEEDS A MERCHANT APPROVAL");
+            assert_eq!(
+                approval.owner_id, token.owner_id,
+                "REDEMPTION APPROVAL WAS GIVEN TO A PREVIOUS OWNER"
+            );
+            approval.merchant_id
+        } else {
+            let approval = self
+                .merchant_redemption_approvals
+                .get(&token_id)
+                .filter(|approval| approval.merchant_id == sender_id)
+                .expect("ONLY THE HOLDER OR A MERCHANT THEY APPROVED CAN REDEEM");
+            assert_eq!(
+                approval.owner_id, token.owner_id,
+                "REDEMPTION APPROVAL WAS GIVEN BY A PREVIOUS OWNER"
+            );
+            sender_id
+        };
+        //the merchant may have been removed since it approved the redemption
+        self.assert_series_merchant(&token, &merchant_id);
+
+        if self.internal_expire_on_touch(&token_id).is_some() {
+            Promise::new(env::predecessor_account_id()).transfer(env::attached_deposit());
+            return None;
+        }
+        //a perk can only be redeemed while it is valid, whatever the validity policy
+        assert!(self.nft_is_valid(token_id), "TOKEN IS NOT VALID");
+
+        let initial_storage_usage = env::storage_usage();
+
+        let mut history = self.internal_redemption_history(&token_id);
+        if let Some(config) = self.redemption_configs.get(&token.series_id.unwrap()) {
+            if let Some(max_redemptions) = config.max_redemptions {
+                assert!(
+                    history.len() < u64::from(max_redemptions),
+                    "TOKEN WAS REDEEMED THE MAXIMUM NUMBER OF TIMES"
+                );
+            }
+            if let Some(last) = history.len().checked_sub(1).and_then(|index| history.get(index)) {
+                assert!(
+                    env::block_timestamp() >= last.redeemed_at + config.cooldown,
+                    "TOKEN CAN BE REDEEMED AGAIN AT :{}",
+                    last.redeemed_at + config.cooldown
+                );
+            }
+        }
+
+        let redemption = Redemption {
+            merchant_id,
+            owner_id: token.owner_id,
+            memo,
+            redeemed_at: env::block_timestamp(),
+        };
+        history.push(&redemption);
+        self.redemptions.insert(&token_id, &history);
+        //every redemption needs a new approval
+        self.redemption_approvals.remove(&token_id);
+        self.merchant_redemption_approvals.remove(&token_id);
+
+        let redeem_log: EventLog = EventLog {
+            standard: BLOCKPERKS_STANDARD_NAME.to_string(),
+            version: BLOCKPERKS_EVENT_VERSION.to_string(),
+            event: EventLogVariant::NftRedeem(vec![NftRedeemLog {
+                owner_id: redemption.owner_id.to_string(),
+                merchant_id: redemption.merchant_id.to_string(),
+                token_ids: vec![token_id.to_string()],
+                memo: redemption.memo.clone(),
+            }]),
+        };
+        env::log_str(&redeem_log.to_string());
+
+        let required_storage_in_bytes = env::storage_usage().saturating_sub(initial_storage_usage);
+        self.internal_refund_deposit(required_storage_in_bytes);
+
+        Some(redemption)
+    }
+
+    //get the redemptions of a token, oldest first
+    pub fn nft_redemptions(
+        &self,
+        token_id: TokenId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<Redemption> {
+        let history = if let Some(history) = self.redemptions.get(&token_id) {
+            history
+        } else {
+            return vec![];
+        };
+
+        //where to start pagination - if we have a from_index, we'll use that - otherwise start from 0 index
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        history
+            .iter()
+            .skip(start as usize)
+            .take(limit.unwrap_or(50) as usize)
+            .collect()
+    }
+
+    pub fn nft_redemption_count(&self, token_id: TokenId) -> u64 {
+        self.redemptions
+            .get(&token_id)
+            .map(|history| history.len())
+            .unwrap_or(0)
+    }
+}
+
+impl Contract {
+    pub(crate) fn assert_series_creator(&self, series_id: &SeriesId) {
+        let series = self.internal_get_series(series_id).expect("SERIES DOESNT EXIST");
+        assert_eq!(
+            env::predecessor_account_id(),
+            series.creator_id,
+            "ONLY SERIES CREATOR CAN MANAGE ITS REDEMPTIONS"
+        );
+    }
+
+    pub(crate) fn assert_series_merchant(&self, token: &Token, merchant_id: &AccountId) {
+        let series_id = token.series_id.expect("ONLY SERIES TOKENS CAN BE REDEEMED");
+        assert!(
+            self.series_merchants
+                .get(&series_id)
+                .map(|merchants| merchants.contains(merchant_id))
+                .unwrap_or(false),
+            "NOT A MERCHANT OF THIS SERIES"
+        );
+    }
+
+    pub(crate) fn internal_redemption_history(&self, token_id: &TokenId) -> Vector<Redemption> {
+        self.redemptions.get(token_id).unwrap_or_else(|| {
+            Vector::new(
+                StorageKey::TokenRedemptionsInner {
+                    token_id: *token_id,
+                }
+                .try_to_vec()
+                .unwrap(),
+            )
+        })
+    }
+
+    //forget everything about the redemptions of a burned token
+    pub(crate) fn internal_clear_redemptions(&mut self, token_id: &TokenId) {
+        if let Some(mut history) = self.redemptions.remove(token_id) {
+            history.clear();
+        }
+        self.redemption_approvals.remove(token_id);
+        self.merchant_redemption_approvals.remove(token_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    const HOUR: u64 = 3_600_000_000_000;
+
+    //alice.near holds a copy of series 0, shop.near is a merchant of the series
+    fn contract_with_perk(config: RedemptionConfig) -> (Contract, TokenId) {
+        let mut contract = new_contract();
+        set_context("owner.near", ONE_NEAR, 0);
+        contract.create_series(empty_metadata(), None, None, None);
+        let token_id = contract
+            .nft_series_mint(0, account("alice.near"), None)
+            .token_id;
+        contract.add_series_merchant(0, account("shop.near"));
+        contract.set_series_redemption(0, config);
+        (contract, token_id)
+    }
+
+    fn redeem_with_shop(contract: &mut Contract, token_id: TokenId, timestamp: u64) -> Redemption {
+        set_context("shop.near", ONE_NEAR, timestamp);
+        contract.approve_redemption(token_id);
+        set_context("alice.near", ONE_NEAR, timestamp);
+        contract.redeem(token_id, None).unwrap()
+    }
+
+    #[test]
+    fn perk_can_be_redeemed_again_after_its_cooldown() {
+        let (mut contract, token_id) = contract_with_perk(RedemptionConfig {
+            max_redemptions: None,
+            cooldown: HOUR,
+        });
+        redeem_with_shop(&mut contract, token_id, 0);
+        let redemption = redeem_with_shop(&mut contract, token_id, HOUR);
+        assert_eq!(redemption.merchant_id, account("shop.near"));
+        assert_eq!(contract.nft_redemption_count(token_id), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "TOKEN CAN BE REDEEMED AGAIN AT :3600000000000")]
+    fn perk_cannot_be_redeemed_during_its_cooldown() {
+        let (mut contract, token_id) = contract_with_perk(RedemptionConfig {
+            max_redemptions: None,
+            cooldown: HOUR,
+        });
+        redeem_with_shop(&mut contract, token_id, 0);
+        redeem_with_shop(&mut contract, token_id, HOUR - 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "TOKEN WAS REDEEMED THE MAXIMUM NUMBER OF TIMES")]
+    fn perk_cannot_be_redeemed_more_than_its_max() {
+        let (mut contract, token_id) = contract_with_perk(RedemptionConfig {
+            max_redemptions: Some(1),
+            cooldown: 0,
+        });
+        redeem_with_shop(&mut contract, token_id, 0);
+        redeem_with_shop(&mut contract, token_id, 1);
+    }
+
+    #[test]
+    fn merchant_redeems_a_bound_perk_with_the_holder_approval() {
+        let (mut contract, token_id) = contract_with_perk(RedemptionConfig {
+            max_redemptions: None,
+            cooldown: 0,
+        });
+        set_context("owner.near", ONE_NEAR, 0);
+        contract.set_token_transferable(token_id, false);
+
+        set_context("alice.near", ONE_NEAR, 0);
+        contract.approve_merchant_redemption(token_id, account("shop.near"));
+        set_context("shop.near", ONE_NEAR, 0);
+        let redemption = contract.redeem(token_id, None).unwrap();
+        assert_eq!(redemption.owner_id, account("alice.near"));
+        //the merchant got no transfer right on the perk
+        assert!(contract
+            .internal_get_token(&token_id)
+            .unwrap()
+            .approved_account_ids
+            .is_empty());
+        assert!(contract
+            .get_merchant_redemption_approval(token_id)
+            .is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "REDEMPTION APPROVAL WAS GIVEN BY A PREVIOUS OWNER")]
+    fn approval_of_a_previous_owner_cannot_be_used() {
+        let (mut contract, token_id) = contract_with_perk(RedemptionConfig {
+            max_redemptions: None,
+            cooldown: 0,
+        });
+        set_context("alice.near", ONE_NEAR, 0);
+        contract.approve_merchant_redemption(token_id, account("shop.near"));
+        contract.internal_transfer(
+            &account("alice.near"),
+            &account("bob.near"),
+            &token_id,
+            None,
+            None,
+        );
+
+        set_context("shop.near", ONE_NEAR, 0);
+        contract.redeem(token_id, None);
+    }
+}
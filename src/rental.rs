@@ -0,0 +1,288 @@
+use crate::*;
+
+//an account allowed to use a token it doesn't own until expires_at
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenUser {
+    pub user_id: AccountId,
+    //block timestamp in nanoseconds at which the right to use the token ends
+    pub expires_at: u64,
+    //paid rentals can't be cut short by the owner
+    pub rented: bool,
+}
+
+//a token the owner lets anyone rent for a number of periods at a price per period
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RentalListing {
+    pub token_id: TokenId,
+    pub owner_id: AccountId,
+    pub price_per_period: U128,
+    //length of a period in nanoseconds
+    pub period: u64,
+    pub max_periods: u32,
+}
+
+#[near_bindgen]
+impl Contract {
+    // LET AN ACCOUNT USE A TOKEN UNTIL AN EXPIRY --- THE OWNER OR AN APPROVED ACCOUNT CAN
+    #[payable]
+    pub fn set_user(&mut self, token_id: TokenId, user_id: AccountId, expires_at: u64) {
+        assert_at_least_one_yocto();
+        let token = self.internal_get_token(&token_id).expect("TOKEN DOESNT EXIST");
+        let sender_id = env::predecessor_account_id();
+        assert!(
+            sender_id == token.owner_id || token.approved_account_ids.contains_key(&sender_id),
+            "ONLY TOKEN OWNER OR AN APPROVED ACCOUNT CAN SET ITS USER"
+        );
+        assert!(
+            expires_at > env::block_timestamp(),
+            "USER EXPIRY MUST BE IN THE FUTURE"
+        );
+        self.assert_not_rented(&token_id);
+
+        let initial_storage_usage = env::storage_usage();
+        self.internal_set_user(&token_id, &user_id, expires_at, false);
+
+        //refund any excess storage if the user attached too much. Whatever wasn't attached is taken from the storage balance.
+        let required_storage_in_bytes = env::storage_usage().saturating_sub(initial_storage_usage);
+        self.internal_refund_deposit(required_storage_in_bytes);
+    }
+
+    //the account using the token, none once the right to use it expired
+    pub fn nft_user_of(&self, token_id: TokenId) -> Option<AccountId> {
+        self.internal_active_user(&token_id).map(|user| user.user_id)
+    }
+
+    pub fn nft_user_expires(&self, token_id: TokenId) -> Option<u64> {
+        self.internal_active_user(&token_id).map(|user| user.expires_at)
+    }
+
+    // LIST A TOKEN FOR RENT --- RENTERS PAY THE PRICE PER PERIOD FOR EACH PERIOD THEY RENT IT
+    #[payable]
+    pub fn list_for_rent(
+        &mut self,
+        token_id: TokenId,
+        price_per_period: U128,
+        period: u64,
+        max_periods: u32,
+    ) -> RentalListing {
+        assert_at_least_one_yocto();
+        let token = self.internal_get_token(&token_id).expect("TOKEN DOESNT EXIST");
+        assert_eq!(
+            env::predecessor_account_id(),
+            token.owner_id,
+            "ONLY TOKEN OWNER CAN LIST IT FOR RENT"
+        );
+        assert!(period > 0, "RENTAL PERIOD MUST BE > 0");
+        assert!(max_periods > 0, "MAX PERIODS MUST BE > 0");
+        self.assert_not_expired_for_sale(&token_id);
+        self.assert_not_listed_for_sale(&token_id);
+
+        let initial_storage_usage = env::storage_usage();
+
+        let listing = RentalListing {
+            token_id,
+            owner_id: token.owner_id,
+            price_per_period,
+            period,
+            max_periods,
+        };
+        self.rental_listings.insert(&token_id, &listing);
+
+        let required_storage_in_bytes = env::storage_usage().saturating_sub(initial_storage_usage);
+        self.internal_refund_deposit(required_storage_in_bytes);
+
+        listing
+    }
+
+    #[payable]
+    pub fn cancel_rental_listing(&mut self, token_id: TokenId) {
+        assert_one_yocto();
+        let listing = self.rental_listings.get(&token_id).expect("TOKEN IS NOT LISTED FOR RENT");
+        assert_eq!(
+            env::predecessor_account_id(),
+            listing.owner_id,
+            "ONLY TOKEN OWNER CAN CANCEL THE RENTAL LISTING"
+        );
+        self.rental_listings.remove(&token_id);
+    }
+
+    pub fn get_rental_listing(&self, token_id: TokenId) -> Option<RentalListing> {
+        self.rental_listings.get(&token_id)
+    }
+
+    // RENT A TOKEN --- THE PRICE IS PAID OUT LIKE A SALE: ROYALTIES, SPLIT PAYMENTS, TREASURY FEE AND THE OWNER
+    #[payable]
+    pub fn rent(&mut self, token_id: TokenId, periods: u32) -> TokenUser {
+        self.assert_not_paused(PauseFeature::Trading);
+        let listing = self.rental_listings.get(&token_id).expect("TOKEN IS NOT LISTED FOR RENT");
+        let token = self.internal_get_token(&token_id).expect("TOKEN DOESNT EXIST");
+        let renter_id = env::predecessor_account_id();
+        assert_ne!(renter_id, token.owner_id, "YOU CANNOT RENT YOUR OWN NFT");
+        self.assert_not_expired_for_sale(&token_id);
+        self.assert_not_listed_for_sale(&token_id);
+        assert!(
+            periods > 0 && periods <= listing.max_periods,
+            "CAN RENT FOR 1 TO {} PERIODS",
+            listing.max_periods
+        );
+        assert!(
+            self.internal_active_user(&token_id).is_none(),
+            "TOKEN IS ALREADY IN USE"
+        );
+
+        let price = listing
+            .price_per_period
+            .0
+            .checked_mul(Balance::from(periods))
+            .expect("RENTAL PRICE IS TOO HIGH");
+        let deposit = env::attached_deposit();
+        assert!(deposit >= price, "DEPOSIT IS LESS THAN PRICE :{}", price);
+
+        let initial_storage_usage = env::storage_usage();
+
+        let expires_at = listing
+            .period
+            .checked_mul(u64::from(periods))
+            .and_then(|duration| duration.checked_add(env::block_timestamp()))
+            .expect("RENTAL PERIOD IS TOO LONG");
+        let user = self.internal_set_user(&token_id, &renter_id, expires_at, true);
+
+        let payout = self.internal_sale_payout(&token, price);
+        let ledger_storage = self.internal_distribute_payout(payout);
+
+        //the new proceeds entries were paid for out of the price
+        let required_storage_in_bytes = env::storage_usage()
+            .saturating_sub(initial_storage_usage)
+            .saturating_sub(ledger_storage);
+        self.internal_buy_refund_deposit(required_storage_in_bytes, price);
+
+        user
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_active_user(&self, token_id: &TokenId) -> Option<TokenUser> {
+        self.token_users
+            .get(token_id)
+            .filter(|user| user.expires_at > env::block_timestamp())
+    }
+
+    pub(crate) fn internal_set_user(
+        &mut self,
+        token_id: &TokenId,
+        user_id: &AccountId,
+        expires_at: u64,
+        rented: bool,
+    ) -> TokenUser {
+        let user = TokenUser {
+            user_id: user_id.clone(),
+            expires_at,
+            rented,
+        };
+        self.token_users.insert(token_id, &user);
+
+        let user_log: EventLog = EventLog {
+            standard: BLOCKPERKS_STANDARD_NAME.to_string(),
+            version: BLOCKPERKS_EVENT_VERSION.to_string(),
+            event: EventLogVariant::UserUpdate(vec![UserUpdateLog {
+                token_id: token_id.to_string(),
+                user_id: user_id.to_string(),
+                expires_at,
+            }]),
+        };
+        env::log_str(&user_log.to_string());
+
+        user
+    }
+
+    pub(crate) fn internal_is_rented(&self, token_id: &TokenId) -> bool {
+        matches!(self.internal_active_user(token_id), Some(user) if user.rented)
+    }
+
+    //a paid rental runs until it expires, whatever the owner does
+    pub(crate) fn assert_not_rented(&self, token_id: &TokenId) {
+        if let Some(user) = self.internal_active_user(token_id) {
+            assert!(
+                !user.rented,
+                "TOKEN IS RENTED UNTIL :{}",
+                user.expires_at
+            );
+        }
+    }
+
+    //a rental could outlast an auction or a dutch listing and keep its buyer from getting the token
+    pub(crate) fn assert_not_listed_for_sale(&self, token_id: &TokenId) {
+        self.assert_not_on_auction(token_id);
+        assert!(
+            self.dutch_listings.get(token_id).is_none(),
+            "TOKEN HAS A DUTCH LISTING"
+        );
+    }
+
+    //the user and the rental listing of the previous owner don't carry over to the next one
+    pub(crate) fn internal_clear_user(&mut self, token_id: &TokenId) {
+        self.token_users.remove(token_id);
+        self.rental_listings.remove(token_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::testing_env;
+    use crate::test_utils::*;
+
+    fn contract_at(timestamp: u64) -> Contract {
+        testing_env!(VMContextBuilder::new().block_timestamp(timestamp).build());
+        Contract::new_default_meta("owner.near".parse().unwrap())
+    }
+
+    fn at(timestamp: u64) {
+        testing_env!(VMContextBuilder::new().block_timestamp(timestamp).build());
+    }
+
+    #[test]
+    fn user_expires_with_the_block_timestamp() {
+        let mut contract = contract_at(100);
+        contract.internal_set_user(&1, &"renter.near".parse().unwrap(), 200, true);
+        assert_eq!(contract.nft_user_of(1), Some("renter.near".parse().unwrap()));
+        assert_eq!(contract.nft_user_expires(1), Some(200));
+
+        at(200);
+        assert_eq!(contract.nft_user_of(1), None);
+        //an expired rental no longer holds the token
+        contract.assert_not_rented(&1);
+    }
+
+    #[test]
+    #[should_panic(expected = "TOKEN IS RENTED UNTIL :200")]
+    fn active_rental_holds_the_token() {
+        let mut contract = contract_at(100);
+        contract.internal_set_user(&1, &"renter.near".parse().unwrap(), 200, true);
+        contract.assert_not_rented(&1);
+    }
+
+    #[test]
+    #[should_panic(expected = "TOKEN IS ON AUCTION")]
+    fn token_on_auction_cannot_be_listed_for_rent() {
+        let mut contract = new_contract();
+        insert_token(&mut contract, 1, "alice.near");
+        set_context("alice.near", ONE_NEAR, 0);
+        contract.create_auction(1, U128(1), U128(1), 100);
+        contract.list_for_rent(1, U128(1), 10, 5);
+    }
+
+    #[test]
+    #[should_panic(expected = "RENTAL PRICE IS TOO HIGH")]
+    fn rental_price_overflow_is_explained() {
+        let mut contract = new_contract();
+        insert_token(&mut contract, 1, "alice.near");
+        set_context("alice.near", ONE_NEAR, 0);
+        contract.list_for_rent(1, U128(u128::MAX), 10, 5);
+        set_context("bob.near", ONE_NEAR, 0);
+        contract.rent(1, 2);
+    }
+}
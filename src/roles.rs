@@ -0,0 +1,139 @@
+use crate::*;
+
+//the roles that can be granted on the contract. The contract owner implicitly has all of them.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Role {
+    //grants and revokes roles
+    Admin,
+    //mints tokens with batch_mint and unique_mint
+    Minter,
+    //sets the transaction fee
+    FeeManager,
+    //pauses and unpauses the contract
+    Pauser,
+}
+
+pub const ALL_ROLES: [Role; 4] = [Role::Admin, Role::Minter, Role::FeeManager, Role::Pauser];
+
+#[near_bindgen]
+impl Contract {
+    #[payable]
+    pub fn grant_role(&mut self, role: Role, account_id: AccountId) {
+        assert_at_least_one_yocto();
+        self.assert_role(Role::Admin);
+        let initial_storage_usage = env::storage_usage();
+
+        let mut members = self.role_members.get(&role).unwrap_or_else(|| {
+            UnorderedSet::new(StorageKey::RoleMembersInner { role }.try_to_vec().unwrap())
+        });
+        if members.insert(&account_id) {
+            self.role_members.insert(&role, &members);
+            self.internal_log_role_change(role, &account_id, true);
+        }
+
+        //refund any excess storage if the user attached too much. Whatever wasn't attached is taken from the storage balance.
+        let required_storage_in_bytes = env::storage_usage() - initial_storage_usage;
+        self.internal_refund_deposit(required_storage_in_bytes);
+    }
+
+    #[payable]
+    pub fn revoke_role(&mut self, role: Role, account_id: AccountId) {
+        assert_one_yocto();
+        self.assert_role(Role::Admin);
+        self.internal_remove_role(role, &account_id);
+    }
+
+    //give up a role the caller holds
+    #[payable]
+    pub fn renounce_role(&mut self, role: Role) {
+        assert_one_yocto();
+        self.internal_remove_role(role, &env::predecessor_account_id());
+    }
+
+    pub fn has_role(&self, role: Role, account_id: AccountId) -> bool {
+        self.internal_has_role(role, &account_id)
+    }
+
+    //Query for the accounts a role was granted to using pagination (the contract owner isn't listed)
+    pub fn get_role_members(
+        &self,
+        role: Role,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<AccountId> {
+        let members = if let Some(members) = self.role_members.get(&role) {
+            members
+        } else {
+            return vec![];
+        };
+
+        //where to start pagination - if we have a from_index, we'll use that - otherwise start from 0 index
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        members.iter()
+            //skip to the index we specified in the start variable
+            .skip(start as usize)
+            //take the first "limit" elements in the vector. If we didn't specify a limit, use 50
+            .take(limit.unwrap_or(50) as usize)
+            .collect()
+    }
+
+    //get all the roles an account has
+    pub fn get_roles_of(&self, account_id: AccountId) -> Vec<Role> {
+        ALL_ROLES
+            .iter()
+            .filter(|role| self.internal_has_role(**role, &account_id))
+            .copied()
+            .collect()
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_has_role(&self, role: Role, account_id: &AccountId) -> bool {
+        account_id == &self.owner_id
+            || self
+                .role_members
+                .get(&role)
+                .map(|members| members.contains(account_id))
+                .unwrap_or(false)
+    }
+
+    //make sure the caller has the role
+    pub(crate) fn assert_role(&self, role: Role) {
+        assert!(
+            self.internal_has_role(role, &env::predecessor_account_id()),
+            "UNAUTHORIZED - REQUIRES {:?} ROLE",
+            role
+        );
+    }
+
+    pub(crate) fn internal_remove_role(&mut self, role: Role, account_id: &AccountId) {
+        if let Some(mut members) = self.role_members.get(&role) {
+            if members.remove(account_id) {
+                self.role_members.insert(&role, &members);
+                self.internal_log_role_change(role, account_id, false);
+            }
+        }
+    }
+
+    pub(crate) fn internal_log_role_change(&self, role: Role, account_id: &AccountId, granted: bool) {
+        let role_log = RoleLog {
+            role,
+            account_id: account_id.to_string(),
+            sender_id: env::predecessor_account_id().to_string(),
+        };
+        let event = if granted {
+            EventLogVariant::RoleGrant(vec![role_log])
+        } else {
+            EventLogVariant::RoleRevoke(vec![role_log])
+        };
+
+        let role_change_log: EventLog = EventLog {
+            standard: BLOCKPERKS_STANDARD_NAME.to_string(),
+            version: BLOCKPERKS_EVENT_VERSION.to_string(),
+            event,
+        };
+        env::log_str(&role_change_log.to_string());
+    }
+}
@@ -1,7 +1,7 @@
 
 use crate::*;
 
-pub trait NonFungibleTokenCore {
+pub trait NonFungibleTokenPayout {
     //calculates the payout for a token given the passed in balance. This is a view method
     fn nft_payout(&self, token_id: TokenId, balance: U128, max_len_payout: u32) -> Payout;
     
@@ -18,129 +18,79 @@ pub trait NonFungibleTokenCore {
 }
 
 #[near_bindgen]
-impl NonFungibleTokenCore for Contract {
+impl NonFungibleTokenPayout for Contract {
 
     //calculates the payout for a token given the passed in balance. This is a view method
     fn nft_payout(&self, token_id: TokenId, balance: U128, max_len_payout: u32) -> Payout {
         //get the token object
-		let token = self.tokens_by_id.get(&token_id).expect("No token");
-
-        //get the owner of the token
-        let owner_id = token.owner_id;
-        //keep track of the total perpetual royalties
-        let mut total_perpetual = 0;
-        //get the u128 version of the passed in balance (which was U128 before)
-        let balance_u128 = u128::from(balance);
-		//keep track of the payout object to send back
-        let mut payout_object = Payout {
-            payout: HashMap::new()
-        };
-        //get the royalty object from token
-		let royalty = token.royalty;
-
-         //get the royalty object from token
-		let splitpayments = token.splitpayments;
-
-        //make sure we're not paying out to too many people (GAS limits this)
-		assert!(royalty.len() as u32 <= max_len_payout, "Market cannot payout to that many receivers");
-
-        assert!(splitpayments.len() as u32 <= max_len_payout, "Market cannot payout to that many receivers");
-
-
-        //go through each key and value in the royalty object
-		for (k, v) in royalty.iter() {
-            //get the key
-			let key = k.clone();
-            //only insert into the payout if the key isn't the token owner (we add their payout at the end)
-			if key != owner_id {
-                //
-				payout_object.payout.insert(key, royalty_to_payout(*v, balance_u128));
-				total_perpetual += *v;
-			}
-		}
-
-        for (k, v) in splitpayments.iter() {
-            //get the key
-			let key = k.clone();
-            //only insert into the payout if the key isn't the token owner (we add their payout at the end)
-			if key != owner_id {
-                //
-				payout_object.payout.insert(key, royalty_to_payout(*v, balance_u128));
-				total_perpetual += *v;
-			}
-		}
-
-		// payout to previous owner who gets 100% - total perpetual royalties
-		payout_object.payout.insert(owner_id, royalty_to_payout(10000 - total_perpetual, balance_u128));
+		let token = self.internal_get_token(&token_id).expect("No token");
 
         //return the payout object
-		payout_object
+		self.internal_payout(&token, balance, max_len_payout)
 	}
 
     //transfers the token to the receiver ID and returns the payout object that should be payed given the passed in balance. 
     #[payable]
     fn nft_transfer_payout(
         &mut self,
-        _receiver_id: AccountId,
+        receiver_id: AccountId,
         token_id: TokenId,
-        _approval_id:Option<u64>,
-        _memo: Option<String>,
+        approval_id:Option<u64>,
+        memo: Option<String>,
         balance: U128,
         max_len_payout: u32,
     ) -> Payout { 
         //assert that the user attached 1 yocto NEAR for security reasons
         assert_one_yocto();
+        self.assert_not_paused(PauseFeature::Trading);
         //get the sender ID
-        let _sender_id = env::predecessor_account_id();
-        //transfer the token to the passed in receiver and get the previous token object back
-        // let previous_token = self.internal_transfer(
-        //     &sender_id,
-        //     &receiver_id,
-        //     &token_id,
-        //     Some(approval_id),
-        //     memo,
-        // );
+        let sender_id = env::predecessor_account_id();
 
-        let previous_token = self.tokens_by_id.get(&token_id).expect("NO TOKEN");
+        //the payout is calculated before the transfer so it still goes to the previous owner
+        let previous_token = self.internal_get_token(&token_id).expect("No token");
+        self.assert_transferable(&token_id, &previous_token);
+        let payout_object = self.internal_payout(&previous_token, balance, max_len_payout);
 
-        //refund the previous token owner for the storage used up by the previous approved account IDs
-        // refund_approved_account_ids(
-        //     previous_token.owner_id.clone(),
-        //     &previous_token.approved_account_ids,
-        // );
-
-        //get the owner of the token
-        let owner_id = previous_token.owner_id;
-        //keep track of the total perpetual royalties
-        let mut total_perpetual = 0;
-        //get the u128 version of the passed in balance (which was U128 before)
-        let balance_u128 = u128::from(balance);
-		//keep track of the payout object to send back
-        let mut payout_object = Payout {
-            payout: HashMap::new()
-        };
-        //get the royalty object from token
-		let royalty = previous_token.royalty;
-
-        //make sure we're not paying out to too many people (GAS limits this)
-		assert!(royalty.len() as u32 <= max_len_payout, "Market cannot payout to that many receivers");
-
-        //go through each key and value in the royalty object
-		for (k, v) in royalty.iter() {
-            //get the key
-			let key = k.clone();
-            //only insert into the payout if the key isn't the token owner (we add their payout at the end)
-			if key != owner_id {
-                //
-				payout_object.payout.insert(key, royalty_to_payout(*v, balance_u128));
-				total_perpetual += *v;
-			}
-		}
+        //transfer the token to the passed in receiver and get the previous token object back
+        let previous_token = self.internal_transfer(
+            &sender_id,
+            &receiver_id,
+            &token_id,
+            approval_id,
+            memo,
+        );
 
-		// payout to previous owner who gets 100% - total perpetual royalties
-		payout_object.payout.insert(owner_id, royalty_to_payout(10000 - total_perpetual, balance_u128));
+        //refund the previous token owner for the storage used up by the previous approved account IDs
+        refund_approved_account_ids(
+            previous_token.owner_id.clone(),
+            &previous_token.approved_account_ids,
+        );
+
+        //split payments only apply to the first sale, like in nft_buy
+        let mut token = self.internal_get_token(&token_id).unwrap();
+        if !token.splitpayments.is_empty() {
+            token.splitpayments = HashMap::new();
+            self.internal_insert_token(&token_id, &token);
+        }
 
         //return the payout object
 		payout_object
     }
-}
\ No newline at end of file
+}
+
+impl Contract {
+    //the royalties and split payments of a token for the passed in balance as a NEP-199 payout
+    pub(crate) fn internal_payout(&self, token: &Token, balance: U128, max_len_payout: u32) -> Payout {
+        let payout = self.internal_royalty_payout(token, balance.0);
+
+        //make sure we're not paying out to too many people (GAS limits this)
+		assert!(payout.len() as u32 <= max_len_payout, "Market cannot payout to that many receivers");
+
+        Payout {
+            payout: payout
+                .into_iter()
+                .map(|(account_id, amount)| (account_id, U128(amount)))
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,485 @@
+use crate::*;
+use crate::nft_core::NonFungibleTokenCore;
+
+pub type SeriesId = u64;
+
+//a series of editions sharing the same metadata. Its copies only store what they override.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Series {
+    pub series_id: SeriesId,
+    pub creator_id: AccountId,
+    pub metadata: TokenMetadata,
+    //how many copies can ever be minted, unlimited if none
+    pub max_supply: Option<u64>,
+    //price the copies are listed at when they're minted
+    pub price: Option<U128>,
+    pub royalty: HashMap<AccountId, u32>,
+    //split payments of the primary sale of a lazily minted copy
+    pub splitpayments: HashMap<AccountId, u32>,
+    //whether buyers mint the copies themselves with nft_buy_series
+    pub lazy_mint: bool,
+    //bytes of the lazy listing itself, paid from the price of the first copy sold
+    pub unpaid_storage: u64,
+    //how many copies were minted so far, burnt ones included
+    pub minted: u64,
+}
+
+//a series as stored in series_by_id
+#[derive(BorshDeserialize, BorshSerialize)]
+pub enum VersionedSeries {
+    V1(Series),
+}
+
+impl From<VersionedSeries> for Series {
+    fn from(series: VersionedSeries) -> Self {
+        match series {
+            VersionedSeries::V1(series) => series,
+        }
+    }
+}
+
+impl TokenMetadata {
+    //fill in the fields a copy doesn't override with the ones of its series
+    pub(crate) fn merged_with(self, series: &TokenMetadata) -> TokenMetadata {
+        TokenMetadata {
+            title: self.title.or_else(|| series.title.clone()),
+            description: self.description.or_else(|| series.description.clone()),
+            media: self.media.or_else(|| series.media.clone()),
+            media_hash: self.media_hash.or_else(|| series.media_hash.clone()),
+            copies: self.copies.or(series.copies),
+            issued_at: self.issued_at.or(series.issued_at),
+            expires_at: self.expires_at.or(series.expires_at),
+            starts_at: self.starts_at.or(series.starts_at),
+            updated_at: self.updated_at.or(series.updated_at),
+            extra: self.extra.or_else(|| series.extra.clone()),
+            reference: self.reference.or_else(|| series.reference.clone()),
+            reference_hash: self.reference_hash.or_else(|| series.reference_hash.clone()),
+            art_id: if self.art_id.is_empty() {
+                series.art_id.clone()
+            } else {
+                self.art_id
+            },
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    // CREATE A SERIES --- ITS COPIES ARE MINTED WITH nft_series_mint
+    #[payable]
+    pub fn create_series(
+        &mut self,
+        metadata: TokenMetadata,
+        max_supply: Option<u64>,
+        price: Option<U128>,
+        royalty: Option<HashMap<AccountId, u32>>,
+    ) -> Series {
+        self.internal_create_series(
+            metadata,
+            max_supply,
+            price,
+            royalty.unwrap_or_default(),
+            HashMap::new(),
+            false,
+        )
+    }
+
+    /*
+        LIST A SERIES FOR LAZY MINTING --- NOTHING IS MINTED UNTIL A BUYER CALLS nft_buy_series,
+        THE STORAGE OF EVERY COPY IS THEN PAID FROM ITS PRICE
+    */
+    pub fn create_lazy_series(
+        &mut self,
+        metadata: TokenMetadata,
+        price: U128,
+        max_supply: Option<u64>,
+        royalty: Option<HashMap<AccountId, u32>>,
+        split_payment: Option<HashMap<AccountId, u32>>,
+    ) -> Series {
+        self.internal_create_series(
+            metadata,
+            max_supply,
+            Some(price),
+            royalty.unwrap_or_default(),
+            split_payment.unwrap_or_default(),
+            true,
+        )
+    }
+
+    /*
+        BUY THE NEXT COPY OF A LAZY SERIES --- IT IS MINTED STRAIGHT TO THE BUYER.
+        DURING THE ALLOWLIST PHASE OF ITS SALE THE BUYER HAS TO PROVE IT IS ON THE ALLOWLIST
+    */
+    #[payable]
+    pub fn nft_buy_series(
+        &mut self,
+        series_id: SeriesId,
+        allowlist_proof: Option<AllowlistProof>,
+    ) -> JsonToken {
+        self.assert_not_paused(PauseFeature::Trading);
+        self.assert_not_paused(PauseFeature::Minting);
+        let mut series = self.internal_get_series(&series_id).expect("SERIES DOESNT EXIST");
+        assert!(series.lazy_mint, "SERIES IS NOT LISTED FOR LAZY MINTING");
+        let price = series.price.expect("NOT FOR SALE").0;
+
+        let buyer_id = env::predecessor_account_id();
+        assert_ne!(buyer_id, series.creator_id, "YOU CANNOT BUY YOUR OWN NFT");
+        let deposit = env::attached_deposit();
+        assert!(deposit >= price, "DEPOSIT IS LESS THAN PRICE :{}", price);
+
+        //the creator sells a copy that doesn't exist yet, so it's paid out like the first sale of a minted token
+        let creator_token = Token {
+            owner_id: series.creator_id.clone(),
+            approved_account_ids: Default::default(),
+            next_approval_id: 0,
+            price: Some(price),
+            royalty: series.royalty.clone(),
+            splitpayments: series.splitpayments.clone(),
+            ft_token_id: None,
+            series_id: Some(series_id),
+            edition: None,
+        };
+
+        //the first sale also pays for the listing
+        let listing_storage = std::mem::take(&mut series.unpaid_storage);
+
+        let initial_storage_usage = env::storage_usage();
+        self.internal_record_series_purchase(series_id, &buyer_id, allowlist_proof);
+        let token_id = self.internal_mint_series_copy(series, &buyer_id, None, None);
+        log_nft_mint(buyer_id.as_str(), &[token_id], None);
+
+        //the storage of the copy is taken from the price before it's paid out
+        let storage_used = env::storage_usage() - initial_storage_usage + listing_storage;
+        let storage_cost = env::storage_byte_cost() * Balance::from(storage_used);
+        assert!(
+            price > storage_cost,
+            "PRICE DOESNT COVER THE STORAGE OF :{}",
+            storage_cost
+        );
+
+        //pay out the royalties, split payments, treasury fee and the creator
+        let payout = self.internal_sale_payout(&creator_token, price - storage_cost);
+        log_nft_sale(&token_id, &creator_token.owner_id, &buyer_id, price, None, &payout);
+        self.internal_distribute_payout(payout);
+
+        //refund whatever was attached on top of the price
+        let refund = deposit - price;
+        if refund > 1 {
+            Promise::new(buyer_id).transfer(refund);
+        }
+
+        self.nft_token(token_id).unwrap()
+    }
+
+    // MINT THE NEXT COPY OF A SERIES --- ONLY THE FIELDS IN `metadata` ARE STORED FOR THE COPY
+    #[payable]
+    pub fn nft_series_mint(
+        &mut self,
+        series_id: SeriesId,
+        receiver_id: AccountId,
+        metadata: Option<TokenMetadata>,
+    ) -> JsonToken {
+        assert_at_least_one_yocto();
+        self.assert_role(Role::Minter);
+        self.assert_not_paused(PauseFeature::Minting);
+        let series = self.internal_get_series(&series_id).expect("SERIES DOESNT EXIST");
+        assert_eq!(
+            env::predecessor_account_id(),
+            series.creator_id,
+            "ONLY SERIES CREATOR CAN MINT ITS COPIES"
+        );
+
+        let initial_storage_usage = env::storage_usage();
+
+        let price = series.price.map(|price| price.0);
+        let token_id = self.internal_mint_series_copy(series, &receiver_id, metadata, price);
+        log_nft_mint(receiver_id.as_str(), &[token_id], None);
+
+        //refund any excess storage if the user attached too much. Whatever wasn't attached is taken from the storage balance.
+        let required_storage_in_bytes = env::storage_usage() - initial_storage_usage;
+        self.internal_refund_deposit(required_storage_in_bytes);
+
+        self.nft_token(token_id).unwrap()
+    }
+
+    pub fn nft_series(&self, series_id: SeriesId) -> Option<Series> {
+        self.internal_get_series(&series_id)
+    }
+
+    //Query for the copies of a series that weren't burnt using pagination
+    pub fn nft_series_tokens(
+        &self,
+        series_id: SeriesId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<JsonToken> {
+        let tokens = if let Some(tokens) = self.series_tokens.get(&series_id) {
+            tokens
+        } else {
+            return vec![];
+        };
+
+        //where to start pagination - if we have a from_index, we'll use that - otherwise start from 0 index
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        tokens.iter()
+            //skip to the index we specified in the start variable
+            .skip(start as usize)
+            //take the first "limit" elements in the vector. If we didn't specify a limit, use 50
+            .take(limit.unwrap_or(50) as usize)
+            .map(|token_id| self.nft_token(token_id).unwrap())
+            .collect()
+    }
+
+    //get how many copies of a series exist (burnt copies aren't counted)
+    pub fn nft_series_supply(&self, series_id: SeriesId) -> U128 {
+        self.series_tokens
+            .get(&series_id)
+            .map(|tokens| U128(tokens.len() as u128))
+            .unwrap_or(U128(0))
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_create_series(
+        &mut self,
+        metadata: TokenMetadata,
+        max_supply: Option<u64>,
+        price: Option<U128>,
+        royalty: HashMap<AccountId, u32>,
+        splitpayments: HashMap<AccountId, u32>,
+        lazy_mint: bool,
+    ) -> Series {
+        //a lazy listing is paid for by its first sale
+        if !lazy_mint {
+            assert_at_least_one_yocto();
+        }
+        self.assert_role(Role::Minter);
+        self.assert_not_paused(PauseFeature::Minting);
+        assert!(max_supply != Some(0), "MAX SUPPLY MUST BE > 0");
+
+        assert!(
+            royalty.len() + splitpayments.len() <= 10,
+            "LIMIT EXCEEDED ONLY 10 ACCOOUNTS ARE ALLOWED"
+        );
+        assert!(
+            royalty.values().chain(splitpayments.values()).sum::<u32>() <= 5000,
+            " LIMIT EXCEEDED ONLY ALLOWED  50% FOR ROYALTIES AND SPLIT PAYMENTS",
+        );
+
+        let initial_storage_usage = env::storage_usage();
+
+        let mut series = Series {
+            series_id: self.next_series_id,
+            creator_id: env::predecessor_account_id(),
+            metadata: TokenMetadata {
+                //the number of copies in existence is the supply cap of the series
+                copies: metadata.copies.or(max_supply),
+                ..metadata
+            },
+            max_supply,
+            price,
+            royalty,
+            splitpayments,
+            lazy_mint,
+            unpaid_storage: 0,
+            minted: 0,
+        };
+        self.next_series_id += 1;
+        self.series_by_id
+            .insert(&series.series_id, &VersionedSeries::V1(series.clone()));
+
+        let series_log: EventLog = EventLog {
+            standard: BLOCKPERKS_STANDARD_NAME.to_string(),
+            version: BLOCKPERKS_EVENT_VERSION.to_string(),
+            event: EventLogVariant::SeriesCreate(vec![SeriesCreateLog {
+                creator_id: series.creator_id.to_string(),
+                series_id: series.series_id.to_string(),
+                max_supply: series.max_supply,
+            }]),
+        };
+        env::log_str(&series_log.to_string());
+
+        let required_storage_in_bytes = env::storage_usage() - initial_storage_usage;
+        if lazy_mint {
+            series.unpaid_storage = required_storage_in_bytes;
+            self.series_by_id
+                .insert(&series.series_id, &VersionedSeries::V1(series.clone()));
+            log_nft_list(vec![NftListLog {
+                owner_id: series.creator_id.to_string(),
+                token_id: None,
+                series_id: Some(series.series_id.to_string()),
+                price: series.price,
+                ft_token_id: None,
+            }]);
+        } else {
+            //refund any excess storage if the user attached too much. Whatever wasn't attached is taken from the storage balance.
+            self.internal_refund_deposit(required_storage_in_bytes);
+        }
+
+        series
+    }
+
+    pub(crate) fn internal_get_series(&self, series_id: &SeriesId) -> Option<Series> {
+        self.series_by_id.get(series_id).map(Series::from)
+    }
+
+    //mint the next edition of a series to the receiver and return its token ID. The caller logs the mint.
+    pub(crate) fn internal_mint_series_copy(
+        &mut self,
+        mut series: Series,
+        receiver_id: &AccountId,
+        metadata: Option<TokenMetadata>,
+        price: Option<Balance>,
+    ) -> TokenId {
+        if let Some(max_supply) = series.max_supply {
+            assert!(series.minted < max_supply, "SERIES IS SOLD OUT");
+        }
+        series.minted += 1;
+        let edition = series.minted;
+
+        let token_id = self.tokens_minted;
+        self.tokens_minted += 1;
+
+        let token = Token {
+            owner_id: receiver_id.clone(),
+            approved_account_ids: Default::default(),
+            next_approval_id: 0,
+            price,
+            royalty: series.royalty.clone(),
+            splitpayments: HashMap::new(),
+            ft_token_id: None,
+            series_id: Some(series.series_id),
+            edition: Some(edition),
+        };
+        self.internal_insert_token(&token_id, &token);
+
+        //only the overrides of the copy are stored, the rest comes from the series
+        let mut metadata = metadata.unwrap_or(TokenMetadata {
+            title: None,
+            description: None,
+            media: None,
+            media_hash: None,
+            copies: None,
+            issued_at: None,
+            expires_at: None,
+            starts_at: None,
+            updated_at: None,
+            extra: None,
+            reference: None,
+            reference_hash: None,
+            art_id: Vec::new(),
+        });
+        metadata.issued_at = Some(env::block_timestamp_ms());
+        self.internal_insert_token_metadata(&token_id, &metadata);
+
+        self.internal_add_token_to_owner(receiver_id, &token_id);
+
+        let mut tokens = self.series_tokens.get(&series.series_id).unwrap_or_else(|| {
+            UnorderedSet::new(
+                StorageKey::SeriesTokensInner {
+                    series_id: series.series_id,
+                }
+                .try_to_vec()
+                .unwrap(),
+            )
+        });
+        tokens.insert(&token_id);
+        self.series_tokens.insert(&series.series_id, &tokens);
+        let series_id = series.series_id;
+        self.series_by_id
+            .insert(&series_id, &VersionedSeries::V1(series));
+
+        token_id
+    }
+
+    //a burnt copy no longer counts towards the supply of its series
+    pub(crate) fn internal_remove_series_token(&mut self, series_id: &SeriesId, token_id: &TokenId) {
+        if let Some(mut tokens) = self.series_tokens.get(series_id) {
+            tokens.remove(token_id);
+            self.series_tokens.insert(series_id, &tokens);
+        }
+    }
+
+    //"1 of 100" for a capped series, "1 of 3" (copies minted so far) for an uncapped one
+    pub(crate) fn internal_edition_label(&self, token: &Token) -> Option<String> {
+        let series = self.internal_get_series(&token.series_id?)?;
+        let edition = token.edition?;
+        Some(format!(
+            "{} of {}",
+            edition,
+            series.max_supply.unwrap_or(series.minted)
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::test_utils::get_logs;
+
+    fn metadata(title: Option<&str>, art_id: Vec<&str>) -> TokenMetadata {
+        TokenMetadata {
+            title: title.map(str::to_string),
+            description: None,
+            media: None,
+            media_hash: None,
+            copies: None,
+            issued_at: None,
+            expires_at: None,
+            starts_at: None,
+            updated_at: None,
+            extra: None,
+            reference: None,
+            reference_hash: None,
+            art_id: art_id.into_iter().map(str::to_string).collect(),
+        }
+    }
+
+    #[test]
+    fn copy_inherits_what_it_doesnt_override() {
+        let series = TokenMetadata {
+            media: Some("ipfs://series".to_string()),
+            copies: Some(100),
+            ..metadata(Some("Series"), vec!["art"])
+        };
+        let copy = TokenMetadata {
+            issued_at: Some(42),
+            ..metadata(Some("Copy"), vec![])
+        };
+
+        let merged = copy.merged_with(&series);
+        assert_eq!(merged.title, Some("Copy".to_string()));
+        assert_eq!(merged.media, Some("ipfs://series".to_string()));
+        assert_eq!(merged.copies, Some(100));
+        assert_eq!(merged.issued_at, Some(42));
+        assert_eq!(merged.art_id, vec!["art".to_string()]);
+    }
+
+    #[test]
+    fn copy_art_ids_replace_the_series_ones() {
+        let merged = metadata(None, vec!["own"]).merged_with(&metadata(None, vec!["art"]));
+        assert_eq!(merged.art_id, vec!["own".to_string()]);
+    }
+    #[test]
+    fn lazy_listing_and_its_sales_are_logged() {
+        let mut contract = new_contract();
+        set_context("owner.near", 0, 0);
+        contract.create_lazy_series(metadata(None, vec![]), U128(ONE_NEAR), None, None, None);
+        let list_log = &get_logs()[1];
+        assert!(list_log.contains(r#""event":"nft_list""#));
+        assert!(list_log.contains(r#""series_id":"0""#));
+        assert!(!list_log.contains("token_id"));
+
+        set_context("bob.near", ONE_NEAR, 0);
+        let token_id = contract.nft_buy_series(0, None).token_id;
+        let sale_log = get_logs()
+            .into_iter()
+            .find(|log| log.contains(r#""event":"nft_sale""#))
+            .expect("SALE WAS NOT LOGGED");
+        assert!(sale_log.contains(&format!(r#""token_id":"{}""#, token_id)));
+        assert!(sale_log.contains(r#""seller_id":"owner.near","buyer_id":"bob.near""#));
+    }
+}
@@ -0,0 +1,209 @@
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    // MAKE A TOKEN TRANSFERABLE OR BOUND TO ITS HOLDER --- A TOKEN OF A BOUND SERIES STAYS BOUND
+    #[payable]
+    pub fn set_token_transferable(&mut self, token_id: TokenId, transferable: bool) {
+        assert_at_least_one_yocto();
+        self.assert_role(Role::Minter);
+        let token = self.internal_get_token(&token_id).expect("TOKEN DOESNT EXIST");
+
+        let initial_storage_usage = env::storage_usage();
+        if transferable {
+            self.non_transferable_tokens.remove(&token_id);
+        } else {
+            self.assert_not_for_sale(&token_id, &token);
+            self.assert_not_in_drop(&token);
+            self.non_transferable_tokens.insert(&token_id);
+        }
+
+        //refund any excess storage if the user attached too much. Whatever wasn't attached is taken from the storage balance.
+        let required_storage_in_bytes = env::storage_usage().saturating_sub(initial_storage_usage);
+        self.internal_refund_deposit(required_storage_in_bytes);
+    }
+
+    // MAKE ALL THE COPIES OF A SERIES TRANSFERABLE OR BOUND TO THEIR HOLDERS --- ONLY ITS CREATOR CAN
+    #[payable]
+    pub fn set_series_transferable(&mut self, series_id: SeriesId, transferable: bool) {
+        assert_at_least_one_yocto();
+        let series = self.internal_get_series(&series_id).expect("SERIES DOESNT EXIST");
+        assert_eq!(
+            env::predecessor_account_id(),
+            series.creator_id,
+            "ONLY SERIES CREATOR CAN SET ITS TRANSFERABILITY"
+        );
+
+        let initial_storage_usage = env::storage_usage();
+        if transferable {
+            self.non_transferable_series.remove(&series_id);
+        } else {
+            //copies already out there may have been bought to be traded
+            assert!(
+                self.series_tokens
+                    .get(&series_id)
+                    .map(|tokens| tokens.is_empty())
+                    .unwrap_or(true),
+                "A SERIES CAN ONLY BE BOUND BEFORE ITS FIRST COPY IS MINTED"
+            );
+            self.non_transferable_series.insert(&series_id);
+        }
+
+        let required_storage_in_bytes = env::storage_usage().saturating_sub(initial_storage_usage);
+        self.internal_refund_deposit(required_storage_in_bytes);
+    }
+
+    pub fn is_transferable(&self, token_id: TokenId) -> bool {
+        let token = self.internal_get_token(&token_id).expect("TOKEN DOESNT EXIST");
+        self.internal_is_transferable(&token_id, &token)
+    }
+
+    pub fn is_series_transferable(&self, series_id: SeriesId) -> bool {
+        !self.non_transferable_series.contains(&series_id)
+    }
+
+    // REASSIGN A BOUND TOKEN TO A NEW ACCOUNT OF ITS HOLDER --- WHEN THE HOLDER LOST THE KEYS OF THE OLD ONE
+    #[payable]
+    pub fn recover_token(&mut self, token_id: TokenId, new_owner_id: AccountId, memo: Option<String>) {
+        assert_one_yocto();
+        self.assert_role(Role::Admin);
+        let token = self.internal_get_token(&token_id).expect("TOKEN DOESNT EXIST");
+        assert!(
+            !self.internal_is_transferable(&token_id, &token),
+            "ONLY NON TRANSFERABLE TOKENS CAN BE RECOVERED"
+        );
+        assert_ne!(
+            token.owner_id, new_owner_id,
+            "The token owner and the receiver should be different"
+        );
+        self.assert_not_on_auction(&token_id);
+
+        self.internal_remove_token_from_owner(&token.owner_id, &token_id);
+        self.internal_add_token_to_owner(&new_owner_id, &token_id);
+
+        //the recovered token starts clean: no approvals and no listing
+        let new_token = Token {
+            owner_id: new_owner_id.clone(),
+            approved_account_ids: Default::default(),
+            price: None,
+            ft_token_id: None,
+            ..token.clone()
+        };
+        self.internal_insert_token(&token_id, &new_token);
+        self.internal_remove_dutch_listing(&token_id);
+        self.internal_clear_user(&token_id);
+
+        //the previous account gets back the storage of its approvals
+        if !token.approved_account_ids.is_empty() {
+            refund_approved_account_ids(token.owner_id.clone(), &token.approved_account_ids);
+        }
+
+        let authorized_id = env::predecessor_account_id().to_string();
+
+        //wallets and indexers follow the owner through the standard transfer event
+        log_nft_transfer(
+            Some(authorized_id.clone()),
+            token.owner_id.as_str(),
+            new_owner_id.as_str(),
+            &[token_id],
+            memo.clone(),
+        );
+
+        let recover_log: EventLog = EventLog {
+            standard: BLOCKPERKS_STANDARD_NAME.to_string(),
+            version: BLOCKPERKS_EVENT_VERSION.to_string(),
+            event: EventLogVariant::TokenRecover(vec![TokenRecoverLog {
+                authorized_id,
+                old_owner_id: token.owner_id.to_string(),
+                new_owner_id: new_owner_id.to_string(),
+                token_ids: vec![token_id.to_string()],
+                memo,
+            }]),
+        };
+        env::log_str(&recover_log.to_string());
+    }
+}
+
+impl Contract {
+    //a token is bound to its holder if it or its series is
+    pub(crate) fn internal_is_transferable(&self, token_id: &TokenId, token: &Token) -> bool {
+        if self.non_transferable_tokens.contains(token_id) {
+            return false;
+        }
+        match token.series_id {
+            Some(series_id) => !self.non_transferable_series.contains(&series_id),
+            None => true,
+        }
+    }
+
+    pub(crate) fn assert_transferable(&self, token_id: &TokenId, token: &Token) {
+        assert!(
+            self.internal_is_transferable(token_id, token),
+            "TOKEN IS NOT TRANSFERABLE"
+        );
+    }
+
+    //a token can't be bound while a buyer may be about to take it
+    pub(crate) fn assert_not_for_sale(&self, token_id: &TokenId, token: &Token) {
+        self.assert_not_listed_for_sale(token_id);
+        assert!(token.price.is_none(), "TOKEN IS LISTED FOR SALE");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    //owner.near auctions token 1 of alice.near and bob.near bids on it
+    fn contract_with_bid() -> Contract {
+        let mut contract = new_contract();
+        insert_token(&mut contract, 1, "alice.near");
+        set_context("alice.near", ONE_NEAR, 0);
+        contract.create_auction(1, U128(ONE_NEAR), U128(1), 100);
+        set_context("bob.near", 2 * ONE_NEAR, 10);
+        contract.place_bid(1);
+        contract
+    }
+
+    #[test]
+    #[should_panic(expected = "TOKEN IS ON AUCTION")]
+    fn token_cannot_be_bound_after_a_bid() {
+        let mut contract = contract_with_bid();
+        set_context("owner.near", 1, 20);
+        contract.set_token_transferable(1, false);
+    }
+
+    #[test]
+    fn settling_a_bound_token_refunds_the_winner() {
+        let mut contract = contract_with_bid();
+        //bound some other way since the bid was placed
+        contract.non_transferable_tokens.insert(&1);
+
+        set_context("carol.near", 0, 100);
+        let auction = contract.settle_auction(1);
+        assert_eq!(auction.highest_bid.unwrap().bidder_id, account("bob.near"));
+        assert!(contract.get_auction(1).is_none());
+        assert_eq!(contract.internal_get_token(&1).unwrap().owner_id, account("alice.near"));
+    }
+
+    #[test]
+    #[should_panic(expected = "TOKEN IS NOT TRANSFERABLE")]
+    fn bound_token_cannot_be_priced() {
+        let mut contract = new_contract();
+        insert_token(&mut contract, 1, "alice.near");
+        contract.non_transferable_tokens.insert(&1);
+        set_context("alice.near", 1, 0);
+        contract.internal_update_price(&1, Some(U128(ONE_NEAR)), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "TOKEN IS NOT TRANSFERABLE")]
+    fn bound_token_cannot_get_a_dutch_listing() {
+        let mut contract = new_contract();
+        insert_token(&mut contract, 1, "alice.near");
+        contract.non_transferable_tokens.insert(&1);
+        set_context("alice.near", ONE_NEAR, 0);
+        contract.create_dutch_listing(1, U128(ONE_NEAR), U128(1), None, 100, PriceCurve::Linear);
+    }
+}
@@ -0,0 +1,159 @@
+use crate::*;
+
+/*
+    bytes locked when an account registers: the storage_deposits entry for an account ID of
+    the maximum length (64 bytes + 4 bytes of Borsh length + 1 byte of prefix), its 16 bytes
+    balance and the 40 bytes of overhead every storage record has.
+*/
+const STORAGE_BALANCE_ENTRY_BYTES: u64 = 64 + 4 + 1 + 16 + 40;
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StorageBalance {
+    pub total: U128,
+    pub available: U128,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StorageBalanceBounds {
+    pub min: U128,
+    pub max: Option<U128>,
+}
+
+pub trait StorageManagement {
+    //deposit NEAR to pay for the storage of mints, approvals and listings of an account (the caller by default)
+    fn storage_deposit(
+        &mut self,
+        account_id: Option<AccountId>,
+        registration_only: Option<bool>,
+    ) -> StorageBalance;
+
+    //withdraw the available storage balance of the caller (all of it by default)
+    fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance;
+
+    //close the storage balance of the caller and get all of it back
+    fn storage_unregister(&mut self, force: Option<bool>) -> bool;
+
+    fn storage_balance_bounds(&self) -> StorageBalanceBounds;
+
+    fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance>;
+}
+
+#[near_bindgen]
+impl StorageManagement for Contract {
+    #[payable]
+    fn storage_deposit(
+        &mut self,
+        account_id: Option<AccountId>,
+        registration_only: Option<bool>,
+    ) -> StorageBalance {
+        let amount = env::attached_deposit();
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        let min_balance = self.storage_balance_min();
+
+        let balance = if let Some(balance) = self.storage_deposits.get(&account_id) {
+            //an already registered account only adds to its balance if it isn't a registration
+            if registration_only.unwrap_or(false) {
+                if amount > 0 {
+                    Promise::new(env::predecessor_account_id()).transfer(amount);
+                }
+                balance
+            } else {
+                balance + amount
+            }
+        } else {
+            assert!(
+                amount >= min_balance,
+                "Must attach at least {} yoctoNEAR to register",
+                min_balance,
+            );
+            //a registration only keeps the minimum balance and refunds the rest
+            if registration_only.unwrap_or(false) {
+                let refund = amount - min_balance;
+                if refund > 0 {
+                    Promise::new(env::predecessor_account_id()).transfer(refund);
+                }
+                min_balance
+            } else {
+                amount
+            }
+        };
+
+        self.storage_deposits.insert(&account_id, &balance);
+        self.internal_storage_balance(balance)
+    }
+
+    #[payable]
+    fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let balance = self
+            .storage_deposits
+            .get(&account_id)
+            .expect("ACCOUNT IS NOT REGISTERED");
+        let available = balance - self.storage_balance_min();
+
+        let amount = amount.map(|amount| amount.0).unwrap_or(available);
+        assert!(
+            amount <= available,
+            "CANNOT WITHDRAW MORE THAN THE AVAILABLE BALANCE :{}",
+            available
+        );
+
+        let balance = balance - amount;
+        self.storage_deposits.insert(&account_id, &balance);
+        if amount > 0 {
+            Promise::new(account_id).transfer(amount);
+        }
+
+        self.internal_storage_balance(balance)
+    }
+
+    /*
+        the storage balance isn't tied to the tokens an account owns (their storage is already paid for)
+        so unregistering always succeeds and `force` has no effect.
+    */
+    #[payable]
+    fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+        assert_one_yocto();
+        let _ = force;
+        let account_id = env::predecessor_account_id();
+
+        if let Some(balance) = self.storage_deposits.remove(&account_id) {
+            if balance > 0 {
+                Promise::new(account_id).transfer(balance);
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        StorageBalanceBounds {
+            min: U128(self.storage_balance_min()),
+            max: None,
+        }
+    }
+
+    fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        self.storage_deposits
+            .get(&account_id)
+            .map(|balance| self.internal_storage_balance(balance))
+    }
+}
+
+impl Contract {
+    //the part of a storage balance that stays locked for the registration itself
+    pub(crate) fn storage_balance_min(&self) -> Balance {
+        Balance::from(STORAGE_BALANCE_ENTRY_BYTES) * env::storage_byte_cost()
+    }
+
+    pub(crate) fn internal_storage_balance(&self, balance: Balance) -> StorageBalance {
+        StorageBalance {
+            total: U128(balance),
+            available: U128(balance.saturating_sub(self.storage_balance_min())),
+        }
+    }
+}
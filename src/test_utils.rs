@@ -0,0 +1,64 @@
+//setup shared by the unit tests of the contract
+use crate::*;
+use near_sdk::test_utils::VMContextBuilder;
+use near_sdk::testing_env;
+
+pub(crate) const ONE_NEAR: Balance = 1_000_000_000_000_000_000_000_000;
+
+pub(crate) fn account(account_id: &str) -> AccountId {
+    account_id.parse().unwrap()
+}
+
+//the next call is made by `predecessor_id` with `deposit` attached at `timestamp` (nanoseconds)
+pub(crate) fn set_context(predecessor_id: &str, deposit: Balance, timestamp: u64) {
+    testing_env!(VMContextBuilder::new()
+        .current_account_id(account("contract.near"))
+        .predecessor_account_id(account(predecessor_id))
+        .signer_account_id(account(predecessor_id))
+        .attached_deposit(deposit)
+        .block_timestamp(timestamp)
+        .build());
+}
+
+//a fresh contract owned by owner.near, who holds every role
+pub(crate) fn new_contract() -> Contract {
+    set_context("owner.near", 0, 0);
+    Contract::new_default_meta(account("owner.near"))
+}
+
+pub(crate) fn empty_metadata() -> TokenMetadata {
+    TokenMetadata {
+        title: None,
+        description: None,
+        media: None,
+        media_hash: None,
+        copies: None,
+        issued_at: None,
+        expires_at: None,
+        starts_at: None,
+        updated_at: None,
+        extra: None,
+        reference: None,
+        reference_hash: None,
+        art_id: vec![],
+    }
+}
+
+//put a token of `owner_id` straight into the state, without royalties, price or series
+pub(crate) fn insert_token(contract: &mut Contract, token_id: TokenId, owner_id: &str) -> Token {
+    let token = Token {
+        owner_id: account(owner_id),
+        approved_account_ids: HashMap::new(),
+        next_approval_id: 0,
+        price: None,
+        royalty: HashMap::new(),
+        splitpayments: HashMap::new(),
+        ft_token_id: None,
+        series_id: None,
+        edition: None,
+    };
+    contract.internal_insert_token(&token_id, &token);
+    contract.internal_insert_token_metadata(&token_id, &empty_metadata());
+    contract.internal_add_token_to_owner(&token.owner_id, &token_id);
+    token
+}
@@ -0,0 +1,504 @@
+use crate::*;
+use std::io::{Error, ErrorKind, Result, Write};
+
+/*
+    every record written by this version of the contract starts with this byte followed by the
+    version of its layout. Records written before versioning existed have no tag: a legacy token
+    starts with the length of its owner ID (2 to 64) and legacy metadata with an Option tag (0 or 1)
+    so they can never be mistaken for a tagged record.
+*/
+const VERSION_MARKER: u8 = 0xFF;
+
+//storage key of the version of the layout the Contract struct was written with (missing for the first one)
+pub(crate) const STATE_VERSION_KEY: &[u8] = b"STATE_VERSION";
+//version of the Contract struct written by this code
+pub(crate) const STATE_VERSION: u8 = 3;
+
+//gas kept by the upgrade call itself, everything else is given to migrate
+#[cfg(target_arch = "wasm32")]
+const GAS_FOR_UPGRADE: near_sdk::Gas = near_sdk::Gas(10_000_000_000_000);
+
+//token layout before tokens could be priced in a fungible token
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct TokenV1 {
+    pub owner_id: AccountId,
+    pub approved_account_ids: HashMap<AccountId, u64>,
+    pub next_approval_id: u64,
+    pub price: Option<Balance>,
+    pub royalty: HashMap<AccountId, u32>,
+    pub splitpayments: HashMap<AccountId, u32>,
+}
+
+//token layout before tokens could be copies of a series
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct TokenV2 {
+    pub owner_id: AccountId,
+    pub approved_account_ids: HashMap<AccountId, u64>,
+    pub next_approval_id: u64,
+    pub price: Option<Balance>,
+    pub royalty: HashMap<AccountId, u32>,
+    pub splitpayments: HashMap<AccountId, u32>,
+    pub ft_token_id: Option<AccountId>,
+}
+
+//a token as stored in tokens_by_id. Older layouts are upgraded when they are read.
+pub enum VersionedToken {
+    V1(TokenV1),
+    V2(TokenV2),
+    V3(Token),
+}
+
+impl From<VersionedToken> for Token {
+    fn from(token: VersionedToken) -> Self {
+        match token {
+            VersionedToken::V1(token) => Token {
+                owner_id: token.owner_id,
+                approved_account_ids: token.approved_account_ids,
+                next_approval_id: token.next_approval_id,
+                price: token.price,
+                royalty: token.royalty,
+                splitpayments: token.splitpayments,
+                ft_token_id: None,
+                series_id: None,
+                edition: None,
+            },
+            VersionedToken::V2(token) => Token {
+                owner_id: token.owner_id,
+                approved_account_ids: token.approved_account_ids,
+                next_approval_id: token.next_approval_id,
+                price: token.price,
+                royalty: token.royalty,
+                splitpayments: token.splitpayments,
+                ft_token_id: token.ft_token_id,
+                series_id: None,
+                edition: None,
+            },
+            VersionedToken::V3(token) => token,
+        }
+    }
+}
+
+impl BorshSerialize for VersionedToken {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        match self {
+            //the first version was never tagged
+            VersionedToken::V1(token) => token.serialize(writer),
+            VersionedToken::V2(token) => {
+                writer.write_all(&[VERSION_MARKER, 2])?;
+                token.serialize(writer)
+            }
+            VersionedToken::V3(token) => {
+                writer.write_all(&[VERSION_MARKER, 3])?;
+                token.serialize(writer)
+            }
+        }
+    }
+}
+
+impl BorshDeserialize for VersionedToken {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self> {
+        match read_version(buf)? {
+            1 => Ok(VersionedToken::V1(TokenV1::deserialize(buf)?)),
+            2 => Ok(VersionedToken::V2(TokenV2::deserialize(buf)?)),
+            3 => Ok(VersionedToken::V3(Token::deserialize(buf)?)),
+            version => Err(unknown_version(version)),
+        }
+    }
+}
+
+//token metadata as stored in token_metadata_by_id. Older layouts are upgraded when they are read.
+pub enum VersionedTokenMetadata {
+    V1(TokenMetadata),
+}
+
+impl From<VersionedTokenMetadata> for TokenMetadata {
+    fn from(metadata: VersionedTokenMetadata) -> Self {
+        match metadata {
+            VersionedTokenMetadata::V1(metadata) => metadata,
+        }
+    }
+}
+
+impl BorshSerialize for VersionedTokenMetadata {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        match self {
+            VersionedTokenMetadata::V1(metadata) => {
+                writer.write_all(&[VERSION_MARKER, 1])?;
+                BorshSerialize::serialize(metadata, writer)
+            }
+        }
+    }
+}
+
+impl BorshDeserialize for VersionedTokenMetadata {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self> {
+        match read_version(buf)? {
+            1 => Ok(VersionedTokenMetadata::V1(BorshDeserialize::deserialize(buf)?)),
+            version => Err(unknown_version(version)),
+        }
+    }
+}
+
+//read the version tag of a record and skip it. An untagged record is a version 1 record.
+fn read_version(buf: &mut &[u8]) -> Result<u8> {
+    match buf {
+        [VERSION_MARKER, version, rest @ ..] => {
+            let version = *version;
+            *buf = rest;
+            Ok(version)
+        }
+        [VERSION_MARKER] => Err(Error::new(ErrorKind::InvalidData, "MISSING RECORD VERSION")),
+        _ => Ok(1),
+    }
+}
+
+fn unknown_version(version: u8) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("UNKNOWN RECORD VERSION :{}", version),
+    )
+}
+
+//layout of the Contract struct before versioning existed
+#[derive(BorshDeserialize, BorshSerialize)]
+pub(crate) struct ContractV1 {
+    pub owner_id: AccountId,
+    pub tokens_per_owner: LookupMap<AccountId, UnorderedSet<TokenId>>,
+    pub tokens_by_id: UnorderedMap<TokenId, VersionedToken>,
+    pub token_metadata_by_id: UnorderedMap<TokenId, VersionedTokenMetadata>,
+    pub metadata: LazyOption<NFTContractMetadata>,
+    pub transaction_fee: TransactionFee,
+    pub tokens_minted: TokenId,
+}
+
+//layout of the Contract struct before series
+#[derive(BorshDeserialize, BorshSerialize)]
+pub(crate) struct ContractV2 {
+    pub owner_id: AccountId,
+    pub pending_owner_id: Option<AccountId>,
+    pub treasury_id: AccountId,
+    pub tokens_per_owner: LookupMap<AccountId, UnorderedSet<TokenId>>,
+    pub tokens_by_id: UnorderedMap<TokenId, VersionedToken>,
+    pub token_metadata_by_id: UnorderedMap<TokenId, VersionedTokenMetadata>,
+    pub metadata: LazyOption<NFTContractMetadata>,
+    pub transaction_fee: TransactionFee,
+    pub tokens_minted: TokenId,
+    pub auctions: UnorderedMap<TokenId, Auction>,
+    pub dutch_listings: UnorderedMap<TokenId, DutchListing>,
+    pub offers_by_id: UnorderedMap<OfferId, Offer>,
+    pub offers_per_target: LookupMap<OfferTarget, UnorderedSet<OfferId>>,
+    pub next_offer_id: OfferId,
+    pub accepted_ft_tokens: UnorderedSet<AccountId>,
+    pub storage_deposits: LookupMap<AccountId, Balance>,
+    pub role_members: LookupMap<Role, UnorderedSet<AccountId>>,
+    pub pause_state: PauseState,
+}
+
+impl From<ContractV1> for ContractV2 {
+    fn from(old: ContractV1) -> Self {
+        //the collections keep their prefixes so their records stay where they are
+        ContractV2 {
+            treasury_id: old.owner_id.clone(),
+            pending_owner_id: None,
+            owner_id: old.owner_id,
+            tokens_per_owner: old.tokens_per_owner,
+            tokens_by_id: old.tokens_by_id,
+            token_metadata_by_id: old.token_metadata_by_id,
+            metadata: old.metadata,
+            transaction_fee: old.transaction_fee,
+            tokens_minted: old.tokens_minted,
+            auctions: UnorderedMap::new(StorageKey::Auctions.try_to_vec().unwrap()),
+            dutch_listings: UnorderedMap::new(StorageKey::DutchListings.try_to_vec().unwrap()),
+            offers_by_id: UnorderedMap::new(StorageKey::OffersById.try_to_vec().unwrap()),
+            offers_per_target: LookupMap::new(StorageKey::OffersPerTarget.try_to_vec().unwrap()),
+            next_offer_id: 0,
+            accepted_ft_tokens: UnorderedSet::new(StorageKey::AcceptedFtTokens.try_to_vec().unwrap()),
+            storage_deposits: LookupMap::new(StorageKey::StorageDeposits.try_to_vec().unwrap()),
+            role_members: LookupMap::new(StorageKey::RoleMembers.try_to_vec().unwrap()),
+            pause_state: PauseState::default(),
+        }
+    }
+}
+
+impl From<ContractV2> for Contract {
+    fn from(old: ContractV2) -> Self {
+        Contract {
+            owner_id: old.owner_id,
+            pending_owner_id: old.pending_owner_id,
+            treasury_id: old.treasury_id,
+            tokens_per_owner: old.tokens_per_owner,
+            tokens_by_id: old.tokens_by_id,
+            token_metadata_by_id: old.token_metadata_by_id,
+            metadata: old.metadata,
+            transaction_fee: old.transaction_fee,
+            tokens_minted: old.tokens_minted,
+            auctions: old.auctions,
+            dutch_listings: old.dutch_listings,
+            offers_by_id: old.offers_by_id,
+            offers_per_target: old.offers_per_target,
+            next_offer_id: old.next_offer_id,
+            accepted_ft_tokens: old.accepted_ft_tokens,
+            storage_deposits: old.storage_deposits,
+            role_members: old.role_members,
+            pause_state: old.pause_state,
+            series_by_id: UnorderedMap::new(StorageKey::SeriesById.try_to_vec().unwrap()),
+            series_tokens: LookupMap::new(StorageKey::SeriesTokens.try_to_vec().unwrap()),
+            next_series_id: 0,
+            voucher_signer: None,
+            used_voucher_nonces: LookupSet::new(StorageKey::UsedVoucherNonces.try_to_vec().unwrap()),
+            series_sales: LookupMap::new(StorageKey::SeriesSales.try_to_vec().unwrap()),
+            series_minted_per_account: LookupMap::new(
+                StorageKey::SeriesMintedPerAccount.try_to_vec().unwrap(),
+            ),
+            campaigns: LookupMap::new(StorageKey::Campaigns.try_to_vec().unwrap()),
+            claimed_bitmaps: LookupMap::new(StorageKey::ClaimedBitmaps.try_to_vec().unwrap()),
+            next_campaign_id: 0,
+            drops: LookupMap::new(StorageKey::Drops.try_to_vec().unwrap()),
+            non_transferable_tokens: LookupSet::new(StorageKey::NonTransferableTokens.try_to_vec().unwrap()),
+            non_transferable_series: LookupSet::new(StorageKey::NonTransferableSeries.try_to_vec().unwrap()),
+            token_users: LookupMap::new(StorageKey::TokenUsers.try_to_vec().unwrap()),
+            rental_listings: LookupMap::new(StorageKey::RentalListings.try_to_vec().unwrap()),
+            validity_policy: ValidityPolicy::default(),
+            expired_tokens: LookupSet::new(StorageKey::ExpiredTokens.try_to_vec().unwrap()),
+            series_merchants: LookupMap::new(StorageKey::SeriesMerchants.try_to_vec().unwrap()),
+            redemption_configs: LookupMap::new(StorageKey::RedemptionConfigs.try_to_vec().unwrap()),
+            redemptions: LookupMap::new(StorageKey::Redemptions.try_to_vec().unwrap()),
+            redemption_approvals: LookupMap::new(StorageKey::RedemptionApprovals.try_to_vec().unwrap()),
+            proceeds: LookupMap::new(StorageKey::Proceeds.try_to_vec().unwrap()),
+            push_payout_limit: DEFAULT_PUSH_PAYOUT_LIMIT,
+            ft_proceeds: LookupMap::new(StorageKey::FtProceeds.try_to_vec().unwrap()),
+            merchant_redemption_approvals: LookupMap::new(
+                StorageKey::MerchantRedemptionApprovals.try_to_vec().unwrap(),
+            ),
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /*
+        called by `upgrade` right after the new code is deployed. It rewrites the Contract struct
+        with the current layout, the tokens and their metadata are upgraded lazily when they are read.
+    */
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let version = env::storage_read(STATE_VERSION_KEY)
+            .map(|version| version[0])
+            .unwrap_or(1);
+
+        let contract = match version {
+            1 => {
+                let old: ContractV1 = env::state_read().expect("NO STATE TO MIGRATE");
+                Contract::from(ContractV2::from(old))
+            }
+            2 => {
+                let old: ContractV2 = env::state_read().expect("NO STATE TO MIGRATE");
+                Contract::from(old)
+            }
+            STATE_VERSION => env::state_read().expect("NO STATE TO MIGRATE"),
+            version => env::panic_str(&format!("CANNOT MIGRATE FROM STATE VERSION :{}", version)),
+        };
+
+        env::storage_write(STATE_VERSION_KEY, &[STATE_VERSION]);
+        contract
+    }
+
+    pub fn get_state_version(&self) -> u8 {
+        env::storage_read(STATE_VERSION_KEY)
+            .map(|version| version[0])
+            .unwrap_or(1)
+    }
+}
+
+/*
+    deploy new code and migrate the state in a single batch of actions so a failing migration
+    also reverts the deployment. The code is passed as the raw input of the call.
+*/
+#[cfg(target_arch = "wasm32")]
+#[no_mangle]
+pub extern "C" fn upgrade() {
+    env::setup_panic_hook();
+
+    let contract: Contract = env::state_read().expect("NO STATE");
+    contract.assert_owner();
+
+    let code = env::input().expect("NO CODE ATTACHED");
+    Promise::new(env::current_account_id())
+        .deploy_contract(code)
+        .function_call(
+            "migrate".to_string(),
+            Vec::new(),
+            0,
+            env::prepaid_gas() - env::used_gas() - GAS_FOR_UPGRADE,
+        );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::testing_env;
+
+    fn owner() -> AccountId {
+        "owner.near".parse().unwrap()
+    }
+
+    fn legacy_token(owner_id: &str) -> TokenV1 {
+        TokenV1 {
+            owner_id: owner_id.parse().unwrap(),
+            approved_account_ids: HashMap::new(),
+            next_approval_id: 0,
+            price: Some(1_000),
+            royalty: HashMap::new(),
+            splitpayments: HashMap::new(),
+        }
+    }
+
+    fn legacy_metadata() -> TokenMetadata {
+        TokenMetadata {
+            title: Some("Legacy".to_string()),
+            description: None,
+            media: None,
+            media_hash: None,
+            copies: None,
+            issued_at: None,
+            expires_at: None,
+            starts_at: None,
+            updated_at: None,
+            extra: None,
+            reference: None,
+            reference_hash: None,
+            art_id: vec!["art".to_string()],
+        }
+    }
+
+    //write the state the way the contract did before versioning existed
+    fn write_legacy_state() {
+        let mut tokens_by_id: UnorderedMap<TokenId, TokenV1> =
+            UnorderedMap::new(StorageKey::TokensById.try_to_vec().unwrap());
+        tokens_by_id.insert(&1, &legacy_token("alice.near"));
+        let mut token_metadata_by_id: UnorderedMap<TokenId, TokenMetadata> =
+            UnorderedMap::new(StorageKey::TokenMetadataById.try_to_vec().unwrap());
+        token_metadata_by_id.insert(&1, &legacy_metadata());
+
+        //same layout as ContractV1, only with the legacy record types
+        #[derive(BorshSerialize)]
+        struct LegacyContract {
+            owner_id: AccountId,
+            tokens_per_owner: LookupMap<AccountId, UnorderedSet<TokenId>>,
+            tokens_by_id: UnorderedMap<TokenId, TokenV1>,
+            token_metadata_by_id: UnorderedMap<TokenId, TokenMetadata>,
+            metadata: LazyOption<NFTContractMetadata>,
+            transaction_fee: TransactionFee,
+            tokens_minted: TokenId,
+        }
+        env::state_write(&LegacyContract {
+            owner_id: owner(),
+            tokens_per_owner: LookupMap::new(StorageKey::TokensPerOwner.try_to_vec().unwrap()),
+            tokens_by_id,
+            token_metadata_by_id,
+            metadata: LazyOption::new(StorageKey::NFTContractMetadata.try_to_vec().unwrap(), None),
+            transaction_fee: TransactionFee { current_fee: 250 },
+            tokens_minted: 2,
+        });
+    }
+
+    #[test]
+    fn migrate_from_legacy_state() {
+        testing_env!(VMContextBuilder::new().build());
+        write_legacy_state();
+
+        let contract = Contract::migrate();
+        assert_eq!(contract.owner_id, owner());
+        assert_eq!(contract.treasury_id, owner());
+        assert_eq!(contract.transaction_fee.current_fee, 250);
+        assert_eq!(contract.tokens_minted, 2);
+        assert_eq!(contract.get_state_version(), STATE_VERSION);
+
+        let token = contract.internal_get_token(&1).unwrap();
+        assert_eq!(token.owner_id, "alice.near".parse::<AccountId>().unwrap());
+        assert_eq!(token.price, Some(1_000));
+        assert!(token.ft_token_id.is_none());
+        let metadata = contract.internal_get_token_metadata(&1).unwrap();
+        assert_eq!(metadata.title, Some("Legacy".to_string()));
+        assert_eq!(metadata.art_id, vec!["art".to_string()]);
+    }
+
+    #[test]
+    fn legacy_token_is_rewritten_with_current_layout() {
+        testing_env!(VMContextBuilder::new().build());
+        write_legacy_state();
+
+        let mut contract = Contract::migrate();
+        let mut token = contract.internal_get_token(&1).unwrap();
+        token.ft_token_id = Some("usdc.near".parse().unwrap());
+        contract.internal_insert_token(&1, &token);
+
+        let raw = contract.tokens_by_id.values_as_vector().get_raw(0).unwrap();
+        assert_eq!(&raw[..2], &[VERSION_MARKER, 3]);
+        let token = contract.internal_get_token(&1).unwrap();
+        assert_eq!(token.ft_token_id, Some("usdc.near".parse().unwrap()));
+    }
+
+    #[test]
+    fn migrate_from_state_before_series() {
+        testing_env!(VMContextBuilder::new().build());
+        let mut tokens_by_id: UnorderedMap<TokenId, VersionedToken> =
+            UnorderedMap::new(StorageKey::TokensById.try_to_vec().unwrap());
+        tokens_by_id.insert(
+            &1,
+            &VersionedToken::V2(TokenV2 {
+                owner_id: "alice.near".parse().unwrap(),
+                approved_account_ids: HashMap::new(),
+                next_approval_id: 0,
+                price: Some(1_000),
+                royalty: HashMap::new(),
+                splitpayments: HashMap::new(),
+                ft_token_id: Some("usdc.near".parse().unwrap()),
+            }),
+        );
+        let mut old = ContractV2::from(ContractV1 {
+            owner_id: owner(),
+            tokens_per_owner: LookupMap::new(StorageKey::TokensPerOwner.try_to_vec().unwrap()),
+            tokens_by_id,
+            token_metadata_by_id: UnorderedMap::new(StorageKey::TokenMetadataById.try_to_vec().unwrap()),
+            metadata: LazyOption::new(StorageKey::NFTContractMetadata.try_to_vec().unwrap(), None),
+            transaction_fee: TransactionFee { current_fee: 250 },
+            tokens_minted: 2,
+        });
+        old.treasury_id = "treasury.near".parse().unwrap();
+        old.pause_state.burning = true;
+        env::state_write(&old);
+        env::storage_write(STATE_VERSION_KEY, &[2]);
+
+        let contract = Contract::migrate();
+        assert_eq!(contract.treasury_id, "treasury.near".parse::<AccountId>().unwrap());
+        assert!(contract.pause_state.burning);
+        assert_eq!(contract.next_series_id, 0);
+        assert_eq!(contract.get_state_version(), STATE_VERSION);
+
+        let token = contract.internal_get_token(&1).unwrap();
+        assert_eq!(token.ft_token_id, Some("usdc.near".parse().unwrap()));
+        assert!(token.series_id.is_none() && token.edition.is_none());
+    }
+
+    #[test]
+    fn migrate_current_state_is_a_noop() {
+        testing_env!(VMContextBuilder::new().predecessor_account_id(owner()).build());
+        let mut contract = Contract::new_default_meta(owner());
+        contract.treasury_id = "treasury.near".parse().unwrap();
+        env::state_write(&contract);
+
+        let contract = Contract::migrate();
+        assert_eq!(contract.treasury_id, "treasury.near".parse::<AccountId>().unwrap());
+        assert_eq!(contract.get_state_version(), STATE_VERSION);
+    }
+
+    #[test]
+    fn unknown_record_version_is_rejected() {
+        let bytes = [VERSION_MARKER, 9, 0];
+        assert!(VersionedToken::try_from_slice(&bytes).is_err());
+        assert!(VersionedTokenMetadata::try_from_slice(&bytes).is_err());
+    }
+}
@@ -0,0 +1,269 @@
+use crate::*;
+use std::collections::BTreeMap;
+
+//what happens to an expired token when expire_tokens, a transfer, an approval or a redemption touches it
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum ExpiryAction {
+    //nothing, the token stays as it is
+    Keep,
+    //the token is kept but marked as expired
+    Mark,
+    //the token is burned
+    Burn,
+}
+
+//how the contract treats tokens outside of their metadata's starts_at / expires_at window
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ValidityPolicy {
+    //expired tokens can't be listed, rented or bought
+    pub enforce_on_sale: bool,
+    pub on_expiry: ExpiryAction,
+}
+
+impl Default for ValidityPolicy {
+    fn default() -> Self {
+        Self {
+            enforce_on_sale: false,
+            on_expiry: ExpiryAction::Keep,
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    //a token is valid from its starts_at (included) until its expires_at (excluded), both in milliseconds
+    pub fn nft_is_valid(&self, token_id: TokenId) -> bool {
+        let metadata = self
+            .internal_get_token_metadata(&token_id)
+            .expect("TOKEN DOESNT EXIST");
+        let now = env::block_timestamp_ms();
+        let started = !matches!(metadata.starts_at, Some(starts_at) if now < starts_at);
+        let expired = matches!(metadata.expires_at, Some(expires_at) if now >= expires_at);
+        started && !expired
+    }
+
+    pub fn nft_is_expired(&self, token_id: TokenId) -> bool {
+        self.expired_tokens.contains(&token_id) || self.internal_has_expired(&token_id)
+    }
+
+    #[payable]
+    pub fn set_validity_policy(&mut self, policy: ValidityPolicy) {
+        assert_one_yocto();
+        self.assert_role(Role::Admin);
+        self.validity_policy = policy;
+    }
+
+    pub fn get_validity_policy(&self) -> ValidityPolicy {
+        self.validity_policy.clone()
+    }
+
+    // APPLY THE EXPIRY POLICY TO EXPIRED TOKENS --- ANYONE CAN CALL IT, RETURNS THE TOKENS MARKED OR BURNED
+    pub fn expire_tokens(&mut self, token_ids: Vec<TokenId>) -> Vec<TokenId> {
+        assert!(
+            token_ids.len() <= 100,
+            "CANNOT EXPIRE MORE THAN 100 TOKENS DUE TO GAS LIMITS"
+        );
+        let action = self.validity_policy.on_expiry;
+        if action == ExpiryAction::Keep {
+            return vec![];
+        }
+        if action == ExpiryAction::Burn {
+            self.assert_not_paused(PauseFeature::Burning);
+        }
+
+        let mut expired = Vec::new();
+        //burned tokens are logged once per owner
+        let mut burned: BTreeMap<AccountId, Vec<TokenId>> = BTreeMap::new();
+        for token_id in token_ids {
+            let token = match self.internal_get_token(&token_id) {
+                Some(token) => token,
+                None => continue,
+            };
+            if !self.internal_apply_expiry(&token_id, &token, action) {
+                continue;
+            }
+            if action == ExpiryAction::Burn {
+                burned.entry(token.owner_id).or_default().push(token_id);
+            }
+            expired.push(token_id);
+        }
+
+        for (owner_id, token_ids) in burned {
+            log_nft_burn(owner_id.as_str(), &token_ids, None);
+        }
+        log_token_expire(&expired, action);
+
+        expired
+    }
+}
+
+fn log_token_expire(token_ids: &[TokenId], action: ExpiryAction) {
+    if token_ids.is_empty() {
+        return;
+    }
+    let expire_log: EventLog = EventLog {
+        standard: BLOCKPERKS_STANDARD_NAME.to_string(),
+        version: BLOCKPERKS_EVENT_VERSION.to_string(),
+        event: EventLogVariant::TokenExpire(vec![TokenExpireLog {
+            token_ids: token_ids.iter().map(|token_id| token_id.to_string()).collect(),
+            action,
+        }]),
+    };
+    env::log_str(&expire_log.to_string());
+}
+
+impl Contract {
+    pub(crate) fn internal_has_expired(&self, token_id: &TokenId) -> bool {
+        let expires_at = self
+            .internal_get_token_metadata(token_id)
+            .and_then(|metadata| metadata.expires_at);
+        matches!(expires_at, Some(expires_at) if env::block_timestamp_ms() >= expires_at)
+    }
+
+    /*
+        apply the expiry policy to a token a transfer, an approval or a redemption touches.
+        Returns the action applied, the caller stops on a burn as there is nothing left to act on.
+        While burning is paused the token is left as it is.
+    */
+    pub(crate) fn internal_expire_on_touch(&mut self, token_id: &TokenId) -> Option<ExpiryAction> {
+        let action = self.validity_policy.on_expiry;
+        if action == ExpiryAction::Keep
+            || (action == ExpiryAction::Burn && self.pause_state.is_paused(PauseFeature::Burning))
+        {
+            return None;
+        }
+        let token = self.internal_get_token(token_id)?;
+        if !self.internal_apply_expiry(token_id, &token, action) {
+            return None;
+        }
+
+        if action == ExpiryAction::Burn {
+            log_nft_burn(token.owner_id.as_str(), &[*token_id], None);
+        }
+        log_token_expire(&[*token_id], action);
+        Some(action)
+    }
+
+    //marks or burns a token that has expired and isn't marked yet, returns whether it did
+    fn internal_apply_expiry(&mut self, token_id: &TokenId, token: &Token, action: ExpiryAction) -> bool {
+        if !self.internal_has_expired(token_id) || self.expired_tokens.contains(token_id) {
+            return false;
+        }
+        //auctions and paid rentals run until they end, their tokens are expired on a later touch.
+        //A token in a drop escrow goes back to its creator or to the claimer first.
+        if self.auctions.get(token_id).is_some()
+            || self.internal_is_rented(token_id)
+            || token.owner_id == env::current_account_id()
+        {
+            return false;
+        }
+
+        match action {
+            ExpiryAction::Mark => {
+                self.expired_tokens.insert(token_id);
+                //a marked token is off the market
+                self.internal_remove_dutch_listing(token_id);
+                self.rental_listings.remove(token_id);
+                if token.price.is_some() {
+                    let token = Token {
+                        price: None,
+                        ft_token_id: None,
+                        ..token.clone()
+                    };
+                    self.internal_insert_token(token_id, &token);
+                }
+            }
+            ExpiryAction::Burn => self.internal_burn(token_id, token),
+            ExpiryAction::Keep => return false,
+        }
+        true
+    }
+
+    //expired tokens can't be listed or sold when the policy says so
+    pub(crate) fn assert_not_expired_for_sale(&self, token_id: &TokenId) {
+        if self.validity_policy.enforce_on_sale {
+            assert!(!self.nft_is_expired(*token_id), "TOKEN HAS EXPIRED");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nft_core::NonFungibleTokenCore;
+    use crate::test_utils::*;
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::testing_env;
+
+    fn at_ms(timestamp_ms: u64) {
+        testing_env!(VMContextBuilder::new()
+            .block_timestamp(timestamp_ms * 1_000_000)
+            .build());
+    }
+
+    fn metadata(starts_at: Option<u64>, expires_at: Option<u64>) -> TokenMetadata {
+        TokenMetadata {
+            title: None,
+            description: None,
+            media: None,
+            media_hash: None,
+            copies: None,
+            issued_at: None,
+            expires_at,
+            starts_at,
+            updated_at: None,
+            extra: None,
+            reference: None,
+            reference_hash: None,
+            art_id: vec![],
+        }
+    }
+
+    #[test]
+    fn token_is_valid_inside_its_window() {
+        at_ms(1_000);
+        let mut contract = Contract::new_default_meta("owner.near".parse().unwrap());
+        contract.internal_insert_token_metadata(&1, &metadata(Some(2_000), Some(3_000)));
+        contract.internal_insert_token_metadata(&2, &metadata(None, None));
+
+        assert!(!contract.nft_is_valid(1));
+        assert!(contract.nft_is_valid(2));
+        at_ms(2_000);
+        assert!(contract.nft_is_valid(1));
+        assert!(!contract.internal_has_expired(&1));
+        at_ms(3_000);
+        assert!(!contract.nft_is_valid(1));
+        assert!(contract.internal_has_expired(&1));
+        assert!(contract.nft_is_valid(2));
+    }
+
+    //alice.near holds token 1, which expired at 1_000 ms, under a burn policy
+    fn contract_with_expired_token() -> Contract {
+        let mut contract = new_contract();
+        contract.validity_policy.on_expiry = ExpiryAction::Burn;
+        insert_token(&mut contract, 1, "alice.near");
+        contract.internal_insert_token_metadata(&1, &metadata(None, Some(1_000)));
+        contract
+    }
+
+    #[test]
+    #[should_panic(expected = "Burning IS PAUSED")]
+    fn expired_tokens_are_not_burned_while_burning_is_paused() {
+        let mut contract = contract_with_expired_token();
+        set_context("owner.near", 1, 0);
+        contract.pause(PauseFeature::Burning);
+        at_ms(2_000);
+        contract.expire_tokens(vec![1]);
+    }
+
+    #[test]
+    fn transferring_an_expired_token_burns_it() {
+        let mut contract = contract_with_expired_token();
+        set_context("alice.near", 1, 2_000_000_000);
+        contract.nft_transfer(account("bob.near"), 1, None, None);
+        assert!(contract.internal_get_token(&1).is_none());
+        assert!(contract.nft_tokens_for_owner(account("bob.near"), None, None).is_empty());
+    }
+}
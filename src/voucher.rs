@@ -0,0 +1,159 @@
+use crate::*;
+use ed25519_dalek::Verifier;
+use near_sdk::json_types::U64;
+use near_sdk::{CurveType, PublicKey};
+
+/*
+    a mint authorized by the backend. The backend signs the Borsh serialization of
+    (contract account ID, voucher) so a voucher can't be replayed on another contract.
+*/
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MintVoucher {
+    pub receiver_id: AccountId,
+    //sha256 of the Borsh serialization of the metadata the token is minted with
+    pub metadata_hash: Base64VecU8,
+    //yoctoNEAR the caller pays to the treasury for the mint
+    pub price: U128,
+    pub nonce: U64,
+    //block timestamp in nanoseconds after which the voucher can no longer be used
+    pub expires_at: U64,
+}
+
+#[near_bindgen]
+impl Contract {
+    // MINT A TOKEN AUTHORIZED BY A VOUCHER SIGNED BY THE BACKEND --- ATTACH THE PRICE AND THE STORAGE
+    #[payable]
+    pub fn mint_with_voucher(
+        &mut self,
+        voucher: MintVoucher,
+        signature: Base64VecU8,
+        metadata: TokenMetadata,
+    ) -> TokenId {
+        self.assert_not_paused(PauseFeature::Minting);
+        let signer = self.voucher_signer.clone().expect("NO VOUCHER SIGNER CONFIGURED");
+
+        assert!(
+            env::block_timestamp() <= voucher.expires_at.0,
+            "VOUCHER HAS EXPIRED"
+        );
+        assert_eq!(
+            env::sha256(&metadata.try_to_vec().unwrap()),
+            voucher.metadata_hash.0,
+            "METADATA DOESNT MATCH THE VOUCHER"
+        );
+        let message = (env::current_account_id(), voucher.clone())
+            .try_to_vec()
+            .unwrap();
+        assert!(
+            verify_signature(&signer, &message, &signature.0),
+            "INVALID VOUCHER SIGNATURE"
+        );
+
+        let price = voucher.price.0;
+        let deposit = env::attached_deposit();
+        assert!(deposit >= price, "DEPOSIT IS LESS THAN PRICE :{}", price);
+
+        let initial_storage_usage = env::storage_usage();
+
+        //a nonce can only be used once
+        assert!(
+            self.used_voucher_nonces.insert(&voucher.nonce.0),
+            "VOUCHER WAS ALREADY USED"
+        );
+
+        let token_id = self.internal_unique_mint(
+            &voucher.receiver_id,
+            &metadata,
+            None,
+            HashMap::new(),
+            HashMap::new(),
+        );
+        log_nft_unique_mint(voucher.receiver_id.as_str(), &[token_id], None);
+
+        if price > 0 {
+            Promise::new(self.treasury_id.clone()).transfer(price);
+        }
+
+        //the rest of the deposit pays for the storage, whatever it doesn't cover is taken from the storage balance
+        let required_storage_in_bytes = env::storage_usage() - initial_storage_usage;
+        self.internal_buy_refund_deposit(required_storage_in_bytes, price);
+
+        token_id
+    }
+
+    // SET THE BACKEND KEY VOUCHERS ARE SIGNED WITH --- NONE TURNS VOUCHER MINTING OFF
+    #[payable]
+    pub fn set_voucher_signer(&mut self, public_key: Option<PublicKey>) {
+        assert_one_yocto();
+        self.assert_role(Role::Admin);
+        if let Some(public_key) = public_key.as_ref() {
+            assert!(
+                public_key.curve_type() == CurveType::ED25519,
+                "VOUCHER SIGNER MUST BE AN ED25519 KEY"
+            );
+        }
+        self.voucher_signer = public_key;
+    }
+
+    pub fn get_voucher_signer(&self) -> Option<PublicKey> {
+        self.voucher_signer.clone()
+    }
+
+    pub fn is_voucher_nonce_used(&self, nonce: U64) -> bool {
+        self.used_voucher_nonces.contains(&nonce.0)
+    }
+}
+
+//check an ed25519 signature. The first byte of a NEAR public key is its curve type.
+pub(crate) fn verify_signature(public_key: &PublicKey, message: &[u8], signature: &[u8]) -> bool {
+    let public_key = match ed25519_dalek::PublicKey::from_bytes(&public_key.as_bytes()[1..]) {
+        Ok(public_key) => public_key,
+        Err(_) => return false,
+    };
+    let signature = match ed25519_dalek::Signature::from_bytes(signature) {
+        Ok(signature) => signature,
+        Err(_) => return false,
+    };
+    public_key.verify(message, &signature).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{ExpandedSecretKey, SecretKey};
+
+    fn keypair(seed: u8) -> (ExpandedSecretKey, ed25519_dalek::PublicKey) {
+        let secret = SecretKey::from_bytes(&[seed; 32]).unwrap();
+        let public = ed25519_dalek::PublicKey::from(&secret);
+        (ExpandedSecretKey::from(&secret), public)
+    }
+
+    fn near_public_key(public: &ed25519_dalek::PublicKey) -> PublicKey {
+        let mut bytes = vec![CurveType::ED25519 as u8];
+        bytes.extend_from_slice(public.as_bytes());
+        PublicKey::try_from(bytes).unwrap()
+    }
+
+    #[test]
+    fn valid_signature_is_accepted() {
+        let (secret, public) = keypair(7);
+        let signature = secret.sign(b"voucher", &public);
+        assert!(verify_signature(
+            &near_public_key(&public),
+            b"voucher",
+            &signature.to_bytes()
+        ));
+    }
+
+    #[test]
+    fn tampered_message_or_other_key_is_rejected() {
+        let (secret, public) = keypair(7);
+        let (_, other_public) = keypair(8);
+        let signature = secret.sign(b"voucher", &public).to_bytes();
+
+        assert!(!verify_signature(&near_public_key(&public), b"vouches", &signature));
+        assert!(!verify_signature(&near_public_key(&other_public), b"voucher", &signature));
+        assert!(!verify_signature(&near_public_key(&public), b"voucher", &signature[..63]));
+    }
+}